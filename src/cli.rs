@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::ClidleError;
+
+/// Command line arguments.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// `--stats-out <path>`: where to write the session stats when quitting
+    pub stats_out: Option<PathBuf>,
+    /// `--debug`: allow showing the debug overlay with F12
+    pub debug: bool,
+    /// `--items <path>`: where to read the items from, `-` for the standard input
+    pub items: Option<String>,
+    /// `--print-config`: print the configuration in use with its defaults, then exit
+    pub print_config: bool,
+    /// `--record <path>`: where to write every event of the game when quitting
+    pub record: Option<PathBuf>,
+    /// `--replay <path>`: play a recorded game again without a terminal
+    pub replay: Option<PathBuf>,
+    /// `--export`: print the save as a text to import in game with `i`, then exit
+    pub export: bool,
+    /// `--list-items`: print every item of the catalog in a table, then exit
+    pub list_items: bool,
+    /// `--owned-csv <path>`: write the owned items of the save as csv, then exit
+    pub owned_csv: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parse the arguments, without the program name.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::UnknownArgument` for an unknown flag, or
+    /// `ClidleError::ArgumentNeedsValue` for a flag missing its value.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, ClidleError> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--debug" => parsed.debug = true,
+                "--print-config" => parsed.print_config = true,
+                "--export" => parsed.export = true,
+                "--list-items" => parsed.list_items = true,
+                "--items" => parsed.items = Some(value(&arg, args.next())?),
+                "--record" => parsed.record = Some(value(&arg, args.next())?.into()),
+                "--replay" => parsed.replay = Some(value(&arg, args.next())?.into()),
+                "--owned-csv" => parsed.owned_csv = Some(value(&arg, args.next())?.into()),
+                "--stats-out" => parsed.stats_out = Some(value(&arg, args.next())?.into()),
+                _ => return Err(ClidleError::UnknownArgument(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// The value following `flag`, if any.
+fn value(flag: &str, next: Option<String>) -> Result<String, ClidleError> {
+    next.ok_or_else(|| ClidleError::ArgumentNeedsValue(flag.to_string()))
+}
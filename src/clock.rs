@@ -0,0 +1,41 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+/// Where the game reads the time, so that it can be driven by hand.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A time that only moves when told to, for replays.
+pub struct ManualClock {
+    now: Cell<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            now: Cell::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
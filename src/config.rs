@@ -0,0 +1,409 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{i18n::Lang, keys::KeyBindings, lines::Lines, theme::Theme, ClidleError};
+
+/// Where the configuration is read from, next to `items.json`.
+pub const CONFIG_PATH: &str = "config.json";
+
+/// Player settings, loaded from `config.json`.
+///
+/// Every field has a default thanks to `#[serde(default)]`, so the file
+/// may only contain the settings you want to change, or not exist at all.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// short names to type instead of the item name: alias -> item name
+    pub aliases: HashMap<String, String>,
+    /// colors of the ui
+    pub theme: Theme,
+    /// language of the ui when the game starts
+    pub language: Lang,
+    /// code lines of a new game
+    pub starting_lines: Lines,
+    /// items owned in a new game: item name -> count
+    pub starting_items: HashMap<String, u64>,
+    /// owned counts worth reaching, `dev next` buys up to the next multiple
+    pub milestone: u64,
+    /// whether `c` codes when pressed with nothing typed yet while buying or selling,
+    /// instead of being the first letter of the item
+    pub code_key_while_typing: bool,
+    /// how many recent purchases are remembered for undo and rebuy
+    pub history_depth: usize,
+    /// how many items are shown on each page of the item list
+    pub page_size: usize,
+    /// how far ahead the production forecast looks at first, in seconds
+    pub forecast_secs: u64,
+    /// upgrades of the `c` key, bought in order with `k`
+    pub click_tiers: Vec<ClickTier>,
+    /// code lines counts celebrated once reached, empty to never be bothered
+    pub thresholds: Vec<u64>,
+    /// decimals shown for code lines and productions, up to `MAX_DECIMAL_PLACES`
+    pub decimal_places: usize,
+    /// whether the game opens paused, waiting for `p` to produce
+    pub start_paused: bool,
+    /// whether the game opens auto-coding, writing a click of lines every second
+    pub auto_code: bool,
+    /// whether quitting with `q`, not Ctrl-C, shows what the session brought until a key press
+    pub session_summary: bool,
+    /// whether this game doesn't save when another running game holds the save
+    pub read_only_when_locked: bool,
+    /// whether the code lines counter rolls toward big changes instead of jumping
+    pub smooth_counter: bool,
+    /// the game waits for the terminal to have at least this many columns
+    pub min_width: u16,
+    /// the game waits for the terminal to have at least this many rows
+    pub min_height: u16,
+    /// how the screen is split when the game starts
+    pub layout: UiLayout,
+    /// whether selling and undoing purchases are forbidden, for a stricter game
+    pub hardcore: bool,
+    /// whether the save is written after every action and tick, and can't be exported nor imported
+    pub ironman: bool,
+    /// how items are shown in the list, unless they have their own `display_template`
+    pub item_template: Option<String>,
+    /// purchases costing more code lines than this must be confirmed, none to never ask
+    pub confirm_above: Option<u64>,
+    /// whether prices are paid with the whole code lines only, or the fractional ones too
+    pub affordability: Affordability,
+    /// code lines a purchase must leave at least, 0 for none
+    pub min_reserve: u64,
+    /// whether selling the last copies of an item must be confirmed
+    pub confirm_last_sell: bool,
+    /// most of the code lines a single purchase may spend, 0.5 for half, none for all
+    pub budget_fraction: Option<f64>,
+    /// discounts on buying many of an item at once, the biggest tier reached applies
+    pub bulk_discounts: Vec<BulkDiscount>,
+    /// the keys of the normal mode, unless the save has its own
+    pub keys: KeyBindings,
+    /// what happens to saved items missing from a new `items.json`
+    pub vanished_items: Vanished,
+    /// whether prices grow with the production per second, see `price_at`
+    pub dynamic_cost: bool,
+    /// how much of the keys the help line shows in normal mode
+    pub help_verbosity: HelpVerbosity,
+    /// whether items can be bought and sold by fractions, like `dev 0.5`
+    pub fractional_items: bool,
+    /// most seconds of production credited at once after the game was suspended
+    pub max_catchup_secs: u64,
+    /// part of the production credited for the time the game was suspended, from 0 to 1
+    pub offline_rate: f64,
+    /// quit (and save) after this many seconds without a key press, for demos
+    pub idle_quit_secs: Option<u64>,
+    /// whether the terminal bell rings on big events: a threshold, a prestige or an upgrade
+    pub bell: bool,
+    /// per how long productions are shown
+    pub rate_unit: RateUnit,
+    /// the input mode the game opens in
+    pub start_mode: StartMode,
+    /// code lines to produce in a game to earn the first commit by prestige
+    pub prestige_lines: u64,
+    /// permanent upgrades bought with commits, see `PrestigeUpgrade`
+    pub prestige_upgrades: Vec<PrestigeUpgrade>,
+    /// prestige without asking once the game produced or holds this many code lines, none to never
+    pub auto_prestige_at: Option<f64>,
+    /// seed of the production noise, drawn from the clock when none
+    pub seed: Option<u64>,
+}
+
+/// A cheaper price for buying at least `min_count` items in one go.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkDiscount {
+    pub min_count: u64,
+    /// taken off the total price, 5 for 5%, at most 49 since selling refunds half
+    pub percent: f64,
+}
+
+/// A permanent upgrade, kept through prestiges.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrestigeUpgrade {
+    /// what to type to buy it
+    pub name: String,
+    /// commits it costs
+    pub cost: u64,
+    /// the production of every item is multiplied by it once bought
+    pub cps_multiplier: f64,
+}
+
+/// Where the game starts, `"normal"`, `"buy"` or `"command"`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    /// one key per action
+    #[default]
+    Normal,
+    /// typing an item to buy
+    Buy,
+    /// typing every action as a command, easier with a screen reader
+    Command,
+}
+
+/// What code lines count to pay a price, `"floored"` or `"exact"`, see `Lines::can_pay`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Affordability {
+    /// only the whole lines, 49.9 lines don't pay for 49
+    #[default]
+    Floored,
+    /// the fractional lines too, 49.9 lines pay for 49
+    Exact,
+}
+
+/// The time productions are shown per, `"second"`, `"minute"` or `"hour"`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateUnit {
+    #[default]
+    Second,
+    Minute,
+    Hour,
+}
+
+impl RateUnit {
+    /// What a production per second is multiplied by to be shown in this unit.
+    pub fn factor(self) -> f64 {
+        match self {
+            RateUnit::Second => 1.,
+            RateUnit::Minute => 60.,
+            RateUnit::Hour => 3600.,
+        }
+    }
+
+    /// The short label after a production, like `/s`.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            RateUnit::Second => "/s",
+            RateUnit::Minute => "/min",
+            RateUnit::Hour => "/h",
+        }
+    }
+}
+
+/// How much the help line of normal mode says, `"full"`, `"short"` or `"hidden"`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HelpVerbosity {
+    /// every key with what it does
+    #[default]
+    Full,
+    /// only the keys
+    Short,
+    /// only the code lines and the key to quit
+    Hidden,
+}
+
+/// What to do with saved items that are not in `items.json` anymore.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vanished {
+    /// give back half of their cost, like selling them
+    #[default]
+    Refund,
+    /// just forget them
+    Drop,
+}
+
+/// How the main screen is split, `"vertical"` or `"columns"` in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UiLayout {
+    /// everything stacked, for narrow terminals
+    #[default]
+    Vertical,
+    /// owned items and messages side by side, for wide terminals
+    Columns,
+}
+
+impl UiLayout {
+    /// The other layout, for the toggle key.
+    pub fn next(self) -> UiLayout {
+        match self {
+            UiLayout::Vertical => UiLayout::Columns,
+            UiLayout::Columns => UiLayout::Vertical,
+        }
+    }
+}
+
+/// Most decimals `decimal_places` can ask for.
+pub const MAX_DECIMAL_PLACES: usize = 4;
+
+/// One upgrade of the code lines written by pressing `c`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClickTier {
+    pub cost: u64,
+    /// the lines per press are multiplied by it once bought
+    pub multiplier: f64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            aliases: HashMap::new(),
+            theme: Theme::default(),
+            language: Lang::default(),
+            starting_lines: Lines::ZERO,
+            starting_items: HashMap::new(),
+            milestone: 25,
+            code_key_while_typing: false,
+            history_depth: 20,
+            page_size: 10,
+            forecast_secs: 60,
+            click_tiers: vec![
+                ClickTier {
+                    cost: 100,
+                    multiplier: 2.,
+                },
+                ClickTier {
+                    cost: 1_000,
+                    multiplier: 3.,
+                },
+                ClickTier {
+                    cost: 10_000,
+                    multiplier: 5.,
+                },
+            ],
+            thresholds: (2..=12).map(|exp| 10u64.pow(exp)).collect(),
+            decimal_places: 2,
+            start_paused: false,
+            auto_code: false,
+            session_summary: true,
+            read_only_when_locked: true,
+            smooth_counter: false,
+            min_width: 40,
+            min_height: 12,
+            layout: UiLayout::default(),
+            hardcore: false,
+            ironman: false,
+            item_template: None,
+            confirm_above: None,
+            affordability: Affordability::default(),
+            min_reserve: 0,
+            confirm_last_sell: false,
+            budget_fraction: None,
+            bulk_discounts: Vec::new(),
+            keys: KeyBindings::default(),
+            vanished_items: Vanished::default(),
+            dynamic_cost: false,
+            help_verbosity: HelpVerbosity::default(),
+            fractional_items: false,
+            max_catchup_secs: 3600,
+            offline_rate: 0.5,
+            idle_quit_secs: None,
+            bell: false,
+            rate_unit: RateUnit::default(),
+            start_mode: StartMode::default(),
+            seed: None,
+            prestige_lines: 1_000_000,
+            auto_prestige_at: None,
+            prestige_upgrades: vec![
+                PrestigeUpgrade {
+                    name: "tests".to_string(),
+                    cost: 1,
+                    cps_multiplier: 1.5,
+                },
+                PrestigeUpgrade {
+                    name: "ci".to_string(),
+                    cost: 5,
+                    cps_multiplier: 2.,
+                },
+                PrestigeUpgrade {
+                    name: "refactoring".to_string(),
+                    cost: 25,
+                    cps_multiplier: 3.,
+                },
+            ],
+        }
+    }
+}
+
+impl Config {
+    /// Read the configuration at `path`, using the defaults if the file is missing.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::ConfigMalformed` if the file exists but can't be read or parsed,
+    /// or if a setting is out of its range.
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ClidleError> {
+        let config: Config = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| ClidleError::ConfigMalformed(e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(ClidleError::ConfigMalformed(e.to_string())),
+        };
+        if config.decimal_places > MAX_DECIMAL_PLACES {
+            return Err(ClidleError::ConfigMalformed(format!(
+                "decimal_places is {}, at most {MAX_DECIMAL_PLACES} are allowed",
+                config.decimal_places
+            )));
+        }
+        if !(0. ..=1.).contains(&config.offline_rate) {
+            return Err(ClidleError::ConfigMalformed(format!(
+                "offline_rate is {}, it must be between 0 and 1",
+                config.offline_rate
+            )));
+        }
+        config.keys.check()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// The configuration `--print-config` prints for a `config.json` holding `content`.
+    fn printed(content: &str) -> serde_json::Value {
+        let dir = std::env::temp_dir().join(format!("clidle-{}-config", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, content).unwrap();
+        let config = Config::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        serde_json::from_str(&serde_json::to_string_pretty(&config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn omitted_settings_are_printed_with_their_defaults() {
+        let printed = printed(r#"{"milestone": 10, "hardcore": true}"#);
+        assert_eq!(printed["milestone"], 10);
+        assert_eq!(printed["hardcore"], true);
+        // rien d'autre n'est donné, tout le reste vient des défauts
+        assert_eq!(printed["history_depth"], 20);
+        assert_eq!(printed["page_size"], 10);
+        assert_eq!(printed["session_summary"], true);
+        assert_eq!(printed["decimal_places"], 2);
+        assert_eq!(printed["click_tiers"].as_array().unwrap().len(), 3);
+        assert!(printed["keys"].is_object());
+        let mut expected = serde_json::to_value(Config::default()).unwrap();
+        expected["milestone"] = 10.into();
+        expected["hardcore"] = true.into();
+        assert_eq!(printed, expected);
+    }
+
+    #[test]
+    fn a_missing_file_prints_the_defaults() {
+        let config = Config::load("clidle-no-such-config.json").unwrap();
+        assert_eq!(
+            serde_json::to_value(config).unwrap(),
+            serde_json::to_value(Config::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn rate_units_convert_from_per_second() {
+        assert_eq!(RateUnit::Second.factor(), 1.);
+        assert_eq!(RateUnit::Minute.factor(), 60.);
+        assert_eq!(RateUnit::Hour.factor(), 3600.);
+        assert_eq!(2.5 * RateUnit::Hour.factor(), 9000.);
+        assert_eq!(
+            [RateUnit::Second, RateUnit::Minute, RateUnit::Hour].map(RateUnit::suffix),
+            ["/s", "/min", "/h"]
+        );
+        let config: Config = serde_json::from_str(r#"{"rate_unit": "minute"}"#).unwrap();
+        assert_eq!(config.rate_unit, RateUnit::Minute);
+        assert_eq!(Config::default().rate_unit, RateUnit::Second);
+    }
+}
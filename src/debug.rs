@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Timings of the game loop, shown with `--debug` to diagnose tick accounting.
+///
+/// Without `--debug` the app holds no `DebugInfo` at all, so nothing is measured.
+#[derive(Debug, Default)]
+pub struct DebugInfo {
+    /// whether the overlay is drawn, toggled with F12
+    pub visible: bool,
+    /// time spent drawing the last frame
+    pub frame_time: Duration,
+    /// how long the loop waits for an input
+    pub poll_timeout: Duration,
+    /// time elapsed between the last two ticks
+    pub tick_delta: Duration,
+    /// code lines added by the last tick
+    pub tick_production: f64,
+}
+
+impl DebugInfo {
+    /// Draws the timings in the top right corner, over the rest of the ui.
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>) {
+        let size = f.size();
+        let width = size.width.min(36);
+        let area = Rect::new(size.width - width, 0, width, size.height.min(6));
+        let lines = vec![
+            Spans::from(Span::raw(format!("frame time: {:?}", self.frame_time))),
+            Spans::from(Span::raw(format!("poll timeout: {:?}", self.poll_timeout))),
+            Spans::from(Span::raw(format!("tick delta: {:?}", self.tick_delta))),
+            Spans::from(Span::raw(format!(
+                "tick production: {:.2}",
+                self.tick_production
+            ))),
+        ];
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Debug")),
+            area,
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::ItemId;
+
+/// One successful buy.
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    pub item_id: ItemId,
+    /// whole unless `fractional_items`
+    pub count: f64,
+    /// code lines paid for all of them
+    pub price: u64,
+    pub at: Instant,
+}
+
+/// Values sampled regularly since the start of the game, in a bounded memory.
+///
+/// Once full, only one sample out of two is kept and values are sampled half as
+/// often from then on, so the samples always span the whole game.
+#[derive(Debug, Clone)]
+pub struct Samples {
+    values: Vec<f64>,
+    /// how many samples are kept at most
+    capacity: usize,
+    /// values pushed for each one kept
+    stride: usize,
+    /// values pushed since the last one kept
+    pending: usize,
+}
+
+impl Samples {
+    /// Samples keeping at most `capacity` values, at least 2.
+    pub fn new(capacity: usize) -> Samples {
+        Samples {
+            values: Vec::new(),
+            capacity: capacity.max(2),
+            stride: 1,
+            pending: 0,
+        }
+    }
+
+    /// Sample `value`, unless it's not its turn to be kept.
+    pub fn push(&mut self, value: f64) {
+        self.pending += 1;
+        if self.pending < self.stride {
+            return;
+        }
+        if self.values.len() == self.capacity {
+            // une valeur sur deux depuis la plus récente, comme si on avait échantillonné
+            // deux fois moins souvent depuis le début
+            let mut kept: Vec<f64> = self.values.iter().rev().step_by(2).copied().collect();
+            kept.reverse();
+            self.values = kept;
+            self.stride *= 2;
+            // celle-ci tombe entre deux échantillons du nouveau rythme
+            return;
+        }
+        self.pending = 0;
+        self.values.push(value);
+    }
+
+    /// The samples, oldest first.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+}
+
+/// The most recent purchases, oldest first, behind undo, rebuy and the recent panel.
+#[derive(Debug)]
+pub struct PurchaseHistory {
+    purchases: VecDeque<Purchase>,
+    /// how many purchases are kept at most
+    depth: usize,
+}
+
+impl PurchaseHistory {
+    pub fn new(depth: usize) -> PurchaseHistory {
+        PurchaseHistory {
+            purchases: VecDeque::with_capacity(depth),
+            depth,
+        }
+    }
+
+    /// Record a purchase, forgetting the oldest ones past the depth.
+    pub fn push(&mut self, purchase: Purchase) {
+        self.purchases.push_back(purchase);
+        while self.purchases.len() > self.depth {
+            self.purchases.pop_front();
+        }
+    }
+
+    /// The most recent purchase.
+    pub fn last(&self) -> Option<&Purchase> {
+        self.purchases.back()
+    }
+
+    /// Forget the most recent purchase, once undone.
+    pub fn pop_last(&mut self) -> Option<Purchase> {
+        self.purchases.pop_back()
+    }
+
+    /// Purchases from the most recent to the oldest.
+    pub fn recent(&self) -> impl Iterator<Item = &Purchase> {
+        self.purchases.iter().rev()
+    }
+}
+
+/// Code lines gained lately, to measure the production really obtained, bonuses,
+/// clicks and variance included, rather than the one the items promise.
+#[derive(Debug)]
+pub struct GainWindow {
+    /// (when, code lines gained), oldest first
+    gains: VecDeque<(Instant, f64)>,
+    /// how far back gains count
+    length: Duration,
+    /// when the window started measuring, it's shorter before a whole `length`
+    since: Instant,
+}
+
+impl GainWindow {
+    pub fn new(length: Duration, since: Instant) -> GainWindow {
+        GainWindow {
+            gains: VecDeque::new(),
+            length,
+            since,
+        }
+    }
+
+    /// Record `lines` gained `at`, forgetting the gains that left the window.
+    pub fn push(&mut self, at: Instant, lines: f64) {
+        self.gains.push_back((at, lines));
+        while self
+            .gains
+            .front()
+            .is_some_and(|(when, _)| at.saturating_duration_since(*when) > self.length)
+        {
+            self.gains.pop_front();
+        }
+    }
+
+    /// Code lines gained per second over the window ending `now`, none before a second.
+    pub fn rate(&self, now: Instant) -> Option<f64> {
+        let span = now.saturating_duration_since(self.since).min(self.length);
+        if span < Duration::from_secs(1) {
+            return None;
+        }
+        let gained: f64 = self
+            .gains
+            .iter()
+            .filter(|(when, _)| now.saturating_duration_since(*when) <= self.length)
+            .map(|(_, lines)| lines)
+            .sum();
+        Some(gained / span.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn purchase(price: u64) -> Purchase {
+        Purchase {
+            item_id: ItemId(0),
+            count: 1.,
+            price,
+            at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn history_drops_the_oldest_purchases() {
+        let mut history = PurchaseHistory::new(3);
+        for price in 1..=5 {
+            history.push(purchase(price));
+        }
+        let prices: Vec<u64> = history.recent().map(|purchase| purchase.price).collect();
+        assert_eq!(prices, [5, 4, 3]);
+        assert_eq!(history.pop_last().map(|purchase| purchase.price), Some(5));
+        assert_eq!(history.last().map(|purchase| purchase.price), Some(4));
+    }
+
+    #[test]
+    fn empty_history() {
+        let mut history = PurchaseHistory::new(0);
+        history.push(purchase(1));
+        assert!(history.last().is_none());
+        assert!(history.pop_last().is_none());
+    }
+
+    /// Samples of `capacity` fed with 1, 2, ... `count`.
+    fn sampled(capacity: usize, count: u32) -> Vec<f64> {
+        let mut samples = Samples::new(capacity);
+        for value in 1..=count {
+            samples.push(value.into());
+        }
+        samples.values().to_vec()
+    }
+
+    #[test]
+    fn samples_keep_everything_until_full() {
+        assert!(sampled(4, 0).is_empty());
+        assert_eq!(sampled(4, 3), [1., 2., 3.]);
+        assert_eq!(sampled(4, 4), [1., 2., 3., 4.]);
+        // au moins deux valeurs, pour pouvoir en garder une sur deux
+        assert_eq!(Samples::new(0).capacity, 2);
+    }
+
+    #[test]
+    fn full_samples_halve_and_sample_half_as_often() {
+        assert_eq!(sampled(4, 5), [2., 4.]);
+        assert_eq!(sampled(4, 6), [2., 4., 6.]);
+        assert_eq!(sampled(4, 8), [2., 4., 6., 8.]);
+        assert_eq!(sampled(4, 10), [4., 8.]);
+        assert_eq!(sampled(4, 12), [4., 8., 12.]);
+        // les échantillons couvrent toujours toute la partie, à intervalles réguliers
+        let values = sampled(8, 1000);
+        assert!(values.len() <= 8);
+        assert!(*values.last().unwrap() > 1000. - 128.);
+        let gaps: Vec<f64> = values.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert!(gaps.iter().all(|gap| *gap == gaps[0]), "{values:?}");
+        assert_eq!(values[0], gaps[0]);
+    }
+
+    #[test]
+    fn the_gain_rate_only_counts_the_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut window = GainWindow::new(Duration::from_secs(10), start);
+        assert_eq!(window.rate(start), None);
+        window.push(at(1), 5.);
+        window.push(at(2), 5.);
+        // moins de dix secondes depuis le début, la moyenne se fait sur ce qui a été joué
+        assert_eq!(window.rate(at(4)), Some(2.5));
+        window.push(at(10), 20.);
+        assert_eq!(window.rate(at(10)), Some(3.));
+        // les gains sortis de la fenêtre ne comptent plus
+        assert_eq!(window.rate(at(12)), Some(2.5));
+        assert_eq!(window.rate(at(13)), Some(2.));
+        window.push(at(30), -4.);
+        assert_eq!(window.gains.len(), 1);
+        assert_eq!(window.rate(at(30)), Some(-0.4));
+    }
+}
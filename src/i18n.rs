@@ -0,0 +1,711 @@
+use serde::{Deserialize, Serialize};
+
+/// Language of the ui, `"english"` or `"french"` in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    English,
+    French,
+}
+
+impl Lang {
+    /// The other language, for the toggle key.
+    pub fn next(self) -> Lang {
+        match self {
+            Lang::English => Lang::French,
+            Lang::French => Lang::English,
+        }
+    }
+}
+
+/// Declares `Msg` with its variants, and `Msg::ALL` listing them all, so that the
+/// list can't miss one.
+macro_rules! messages {
+    ($($msg:ident,)*) => {
+        /// Every sentence shown to the player.
+        ///
+        /// `{}` are placeholders filled in order by `tf`.
+        #[derive(Debug, Clone, Copy)]
+        pub enum Msg {
+            $($msg,)*
+        }
+
+        impl Msg {
+            /// Every message, in the order of `Msg`.
+            #[cfg(test)]
+            pub const ALL: &'static [Msg] = &[$(Msg::$msg,)*];
+        }
+    };
+}
+
+messages! {
+    HardcoreTag,
+    Owning,
+    Press,
+    ToExit,
+    ToCode,
+    ToUpgradeClick,
+    ToPause,
+    ToAutoCode,
+    ToStopAutoCode,
+    Paused,
+    ToResume,
+    ToStartBuying,
+    ToStartSelling,
+    ToSellWorst,
+    ToSearch,
+    ToUndo,
+    ToRebuy,
+    ToStartOver,
+    ToPrestige,
+    ToOpenShop,
+    ToFavorite,
+    ToShowFavorites,
+    ToShowAll,
+    ToBuyFavorites,
+    FavoriteHelp,
+    ToStopFavoriting,
+    ToToggleFavorite,
+    ToCompare,
+    CompareHelp,
+    ToStopComparing,
+    ToAddToComparison,
+    CompareTitle,
+    CompareEmpty,
+    CompareCost,
+    CompareCps,
+    CompareEfficiency,
+    CompareBreakEven,
+    CompareNeverBreaksEven,
+    FavoriteAdded,
+    FavoriteRemoved,
+    ItemUnlocked,
+    ConfirmPrestige,
+    ToConfirmPrestige,
+    ShopHelp,
+    ToStopShopping,
+    ToBuyUpgrade,
+    UpgradeLine,
+    UpgradeBought,
+    ToConfirmReset,
+    ConfirmBuy,
+    ToConfirmBuy,
+    ConfirmUnlock,
+    ToConfirmUnlock,
+    ConfirmSell,
+    ToConfirmSell,
+    ToChangeForecast,
+    ToSwitchLayout,
+    ToStartImport,
+    ToStartCommand,
+    CommandHelp,
+    ToStopCommands,
+    ToRunCommand,
+    ToSwitchLanguage,
+    ToFocus,
+    ToLeaveFocus,
+    FocusLines,
+    FocusCps,
+    PerSecond,
+    PerMinute,
+    PerHour,
+    ToStopImporting,
+    ToImport,
+    ToStopBuying,
+    ToBuy,
+    ToPickQuantity,
+    SpinnerHelp,
+    ToChangeQuantity,
+    ToTypeAgain,
+    ToStopSelling,
+    ToSell,
+    ToStopSearching,
+    ToBuyFirstMatch,
+    InputTitle,
+    OwnedTitle,
+    OwnedLine,
+    ItemLine,
+    PaysOffIn,
+    NeverPaysOff,
+    Free,
+    Locked,
+    TopTitle,
+    RecentTitle,
+    PreviewTitle,
+    PreviewDiscount,
+    OwnedHistoryTitle,
+    DescriptionTitle,
+    PreviewLines,
+    PreviewCps,
+    PreviewNextIn,
+    PreviewNeverNext,
+    RecentLine,
+    MessagesTitle,
+    LosingLines,
+    LinesStable,
+    LinesGrowing,
+    MeasuredRate,
+    DoubleCpsAdvice,
+    ErrorLine,
+    RepeatedErrorLine,
+    StatusLine,
+    ClickTier,
+    NextClickTier,
+    LastClickTier,
+    ModeNormal,
+    ModeBuy,
+    ModeSpinner,
+    ModeSell,
+    ModeSearch,
+    ModeConfirmReset,
+    ModeConfirmPrestige,
+    ModeShop,
+    ModeFavorite,
+    ModeCompare,
+    ModeConfirmBuy,
+    ModeConfirmUnlock,
+    ModeConfirmSell,
+    ModeImport,
+    ModeCommand,
+    SearchTitle,
+    MatchesTitle,
+    SummaryTitle,
+    TerminalTooSmall,
+    SummaryLines,
+    SummaryPurchases,
+    SummaryThresholds,
+    SummaryTime,
+    SummaryAnyKey,
+    MatchLine,
+    BoughtOne,
+    BoughtMany,
+    Sold,
+    Undone,
+    GameReset,
+    BudgetTruncated,
+    AutoCodeOn,
+    AutoCodeOff,
+    Prestiged,
+    AutoPrestiged,
+    UpgradePurchased,
+    SaveImported,
+    ItemVanished,
+    UnknownItemDropped,
+    ClickUpgraded,
+    NothingToSell,
+    ThresholdReached,
+    CaughtUp,
+    InvariantsHold,
+    FastForward,
+    NormalSpeed,
+    UnrecognizedKey,
+    InvariantBroken,
+    UnknownItem,
+    DidYouMean,
+    ItemMaxedOut,
+    NotEnoughLines,
+    ItemLocked,
+    UnlockTooExpensive,
+    ReserveBreached,
+    OverBudget,
+    NotOwned,
+    UnknownCommand,
+    MacroStepFailed,
+    MacroStepUnconfirmed,
+    MissingItem,
+    InvalidQuantity,
+    NoPurchaseToUndo,
+    NoClickTierLeft,
+    NothingToPrestige,
+    UpgradeNotKnown,
+    NoFavorite,
+    UpgradeAlreadyBought,
+    NotEnoughCommits,
+    LinesNotANumber,
+    ForbiddenInHardcore,
+    ForbiddenInIronman,
+    KeyConflict,
+    ProductionNotFinite,
+    LinesNotFinite,
+    ImportInvalid,
+    ClickTierTooExpensive,
+    CannotRead,
+    FileMalformed,
+    SynergyItemMissing,
+    UnknownArgument,
+    ArgumentNeedsValue,
+    SaveFailed,
+    SaveLocked,
+    SaveLockedReadOnly,
+    ReplayDiffers,
+}
+
+/// The sentence `msg` in `lang`.
+pub fn t(msg: Msg, lang: Lang) -> &'static str {
+    // une colonne par langue, dans l'ordre de `Lang`
+    let [english, french] = match msg {
+        Msg::HardcoreTag => ["[HARDCORE] ", "[HARDCORE] "],
+        Msg::Owning => ["Owning {} code lines, ", "{} lignes de code, "],
+        Msg::Press => ["Press ", "Appuyer sur "],
+        Msg::ToExit => [" to exit, ", " pour quitter, "],
+        Msg::ToCode => [" to code, ", " pour coder, "],
+        Msg::ToUpgradeClick => [" to code faster, ", " pour coder plus vite, "],
+        Msg::ToPause => [" to pause, ", " pour mettre en pause, "],
+        Msg::ToAutoCode => [" to code automatically, ", " pour coder automatiquement, "],
+        Msg::ToStopAutoCode => [
+            " to stop coding automatically, ",
+            " pour arrêter de coder automatiquement, ",
+        ],
+        Msg::Paused => ["paused, press ", "en pause, appuyer sur "],
+        Msg::ToResume => [" to start producing", " pour lancer la production"],
+        Msg::ToStartBuying => [" to start buying, ", " pour acheter, "],
+        Msg::ToStartSelling => [" to start selling, ", " pour vendre, "],
+        Msg::ToSellWorst => [
+            " to sell the least productive, ",
+            " pour vendre le moins productif, ",
+        ],
+        Msg::ToSearch => [" to search, ", " pour chercher, "],
+        Msg::ToUndo => [
+            " to undo the last purchase, ",
+            " pour annuler le dernier achat, ",
+        ],
+        Msg::ToRebuy => [" to buy it again, ", " pour le racheter, "],
+        Msg::ToChangeForecast => [" to change the forecast, ", " pour changer la prévision, "],
+        Msg::ToStartOver => [" to start a new game, ", " pour recommencer, "],
+        Msg::ToPrestige => [" to prestige, ", " pour un prestige, "],
+        Msg::ToOpenShop => [" to spend commits, ", " pour dépenser ses commits, "],
+        Msg::ToFavorite => [" to mark a favorite, ", " pour marquer un favori, "],
+        Msg::ToShowFavorites => [" to list only favorites, ", " pour ne lister que les favoris, "],
+        Msg::ToShowAll => [" to list all items, ", " pour lister tous les items, "],
+        Msg::ToBuyFavorites => [" to buy one of each favorite, ", " pour acheter un de chaque favori, "],
+        Msg::FavoriteHelp => [
+            "Type an item to mark or unmark as favorite. ",
+            "Taper un item à marquer ou démarquer comme favori. ",
+        ],
+        Msg::ToStopFavoriting => [" to stop, ", " pour arrêter, "],
+        Msg::ToToggleFavorite => [" to mark it", " pour le marquer"],
+        Msg::FavoriteAdded => ["{} is a favorite", "{} est un favori"],
+        Msg::ToCompare => [" to compare two items, ", " pour comparer deux items, "],
+        Msg::CompareHelp => [
+            "Type two items to compare. ",
+            "Taper deux items à comparer. ",
+        ],
+        Msg::ToStopComparing => [" to stop, ", " pour arrêter, "],
+        Msg::ToAddToComparison => [" to compare it", " pour le comparer"],
+        Msg::CompareTitle => ["Comparison", "Comparaison"],
+        Msg::CompareEmpty => ["No item yet", "Pas encore d'item"],
+        Msg::CompareCost => ["Price: {}", "Prix : {}"],
+        Msg::CompareCps => ["Production: {}{}", "Production : {}{}"],
+        Msg::CompareEfficiency => [
+            "Per 1000 lines spent: {}{}",
+            "Pour 1000 lignes dépensées : {}{}",
+        ],
+        Msg::CompareBreakEven => ["Pays for itself in {}s", "Rentabilisé en {} s"],
+        Msg::CompareNeverBreaksEven => ["Never pays for itself", "Jamais rentabilisé"],
+        Msg::FavoriteRemoved => ["{} is no longer a favorite", "{} n'est plus un favori"],
+        Msg::ItemUnlocked => [
+            "{} unlocked for {} code lines, it can be bought now",
+            "{} débloqué pour {} lignes de code, il peut être acheté maintenant",
+        ],
+        Msg::ConfirmPrestige => [
+            "Start over for {} commits? ",
+            "Recommencer pour {} commits ? ",
+        ],
+        Msg::ToConfirmPrestige => [" to confirm, any other key to cancel", " pour confirmer, toute autre touche pour annuler"],
+        Msg::ShopHelp => [
+            "{} commits to spend, type an upgrade. ",
+            "{} commits à dépenser, taper une amélioration. ",
+        ],
+        Msg::ToStopShopping => [" to leave the shop, ", " pour quitter la boutique, "],
+        Msg::ToBuyUpgrade => [" to buy it", " pour l'acheter"],
+        Msg::UpgradeLine => [
+            "{}: {} commits, production x{}",
+            "{} : {} commits, production x{}",
+        ],
+        Msg::UpgradeBought => [" (bought)", " (achetée)"],
+        Msg::ToConfirmReset => [
+            " to start a new game, any other key to cancel",
+            " pour recommencer une partie, n'importe quelle autre touche pour annuler",
+        ],
+        Msg::ConfirmBuy => [
+            "Buy {} {} for {} code lines? ",
+            "Acheter {} {} pour {} lignes de code ? ",
+        ],
+        Msg::ToConfirmBuy => [
+            " to buy, any other key to cancel",
+            " pour acheter, n'importe quelle autre touche pour annuler",
+        ],
+        Msg::ConfirmUnlock => [
+            "{} is locked, unlock it for {} code lines? ",
+            "{} est verrouillé, le débloquer pour {} lignes de code ? ",
+        ],
+        Msg::ToConfirmUnlock => [
+            " to unlock, any other key to cancel",
+            " pour débloquer, n'importe quelle autre touche pour annuler",
+        ],
+        Msg::ConfirmSell => [
+            "Sell your last {} {}? ",
+            "Vendre vos derniers {} {} ? ",
+        ],
+        Msg::ToConfirmSell => [
+            " to sell, any other key to cancel",
+            " pour vendre, n'importe quelle autre touche pour annuler",
+        ],
+        Msg::ToSwitchLayout => [" to switch layout, ", " pour changer la disposition, "],
+        Msg::ToStartImport => [" to import a save, ", " pour importer une sauvegarde, "],
+        Msg::ToStartCommand => [" to type commands, ", " pour taper des commandes, "],
+        Msg::CommandHelp => [
+            "Commands: buy <item> [count], sell <item>, code, upgrade, undo, pause, quit. ",
+            "Commandes : buy <item> [quantité], sell <item>, code, upgrade, undo, pause, quit. ",
+        ],
+        Msg::ToStopCommands => [" to stop typing commands, ", " pour arrêter les commandes, "],
+        Msg::ToRunCommand => [" to run it", " pour l'exécuter"],
+        Msg::ToStopImporting => [" to stop importing, ", " pour arrêter d'importer, "],
+        Msg::ToImport => [" to import", " pour importer"],
+        Msg::ToFocus => [" to only show the counter, ", " pour n'afficher que le compteur, "],
+        Msg::ToLeaveFocus => [" to show everything.", " pour tout afficher."],
+        Msg::FocusLines => ["{} code lines", "{} lignes de code"],
+        Msg::FocusCps => ["{} {}", "{} {}"],
+        Msg::PerSecond => ["per second", "par seconde"],
+        Msg::PerMinute => ["per minute", "par minute"],
+        Msg::PerHour => ["per hour", "par heure"],
+        Msg::ToSwitchLanguage => [" to switch language.", " pour changer de langue."],
+        Msg::ToStopBuying => [" to stop buying, ", " pour arrêter d'acheter, "],
+        Msg::ToBuy => [" buy", " pour acheter"],
+        Msg::ToPickQuantity => [" to pick a quantity, ", " pour choisir une quantité, "],
+        Msg::SpinnerHelp => ["Buying {} {}. ", "Acheter {} {}. "],
+        Msg::ToChangeQuantity => [" to change the quantity, ", " pour changer la quantité, "],
+        Msg::ToTypeAgain => [" to type again, ", " pour retaper, "],
+        Msg::ToStopSelling => [" to stop selling, ", " pour arrêter de vendre, "],
+        Msg::ToSell => [" to sell", " pour vendre"],
+        Msg::ToStopSearching => [" to stop searching, ", " pour arrêter de chercher, "],
+        Msg::ToBuyFirstMatch => [
+            " to buy the first match",
+            " pour acheter le premier résultat",
+        ],
+        Msg::InputTitle => ["Input", "Saisie"],
+        Msg::OwnedTitle => [
+            "Owned ({} bought since the start, at best {} {}, {} {} on average)",
+            "Possédés ({} achetés depuis le début, au mieux {} {}, {} {} en moyenne)",
+        ],
+        Msg::OwnedLine => [
+            "Owning {} {} producing a total of {} code line {}",
+            "{} {} produisant au total {} lignes de code {}",
+        ],
+        Msg::ItemLine => [
+            "Buy {}(as {}) producing {} code lines {}",
+            "Acheter {} ({}) produisant {} lignes de code {}",
+        ],
+        Msg::PaysOffIn => [", pays for itself in {}s", ", rentabilisé en {} s"],
+        Msg::NeverPaysOff => [", never pays for itself", ", jamais rentabilisé"],
+        Msg::Free => [", free", ", gratuit"],
+        Msg::Locked => [", locked ({} lines to unlock)", ", verrouillé ({} lignes pour débloquer)"],
+        Msg::TopTitle => ["Top producers", "Meilleurs producteurs"],
+        Msg::RecentTitle => ["Recent purchases", "Achats récents"],
+        Msg::PreviewTitle => ["Buying {} {}", "Acheter {} {}"],
+        Msg::PreviewDiscount => [", {}% off", ", remise de {} %"],
+        Msg::OwnedHistoryTitle => ["{} owned", "{} possédés"],
+        Msg::DescriptionTitle => ["About", "À propos"],
+        Msg::PreviewLines => ["Lines: {} -> {}", "Lignes : {} -> {}"],
+        Msg::PreviewCps => ["Production: {} -> {} {}", "Production : {} -> {} {}"],
+        Msg::PreviewNextIn => ["Another one in {}s", "Un de plus dans {} s"],
+        Msg::PreviewNeverNext => ["Never another one", "Jamais un de plus"],
+        Msg::RecentLine => [
+            "{} {} for {} lines, {}s ago",
+            "{} {} pour {} lignes, il y a {} s",
+        ],
+        Msg::MessagesTitle => [
+            "Messages (page {}/{}, PageUp/PageDown/Home/End to browse)",
+            "Messages (page {}/{}, PageUp/PageDown/Home/End pour naviguer)",
+        ],
+        Msg::LosingLines => [
+            "Warning: losing {} code lines {}, none left in {} s",
+            "Attention : {} lignes de code perdues {}, plus aucune dans {} s",
+        ],
+        Msg::LinesStable => ["Code lines are stable", "Les lignes de code sont stables"],
+        Msg::LinesGrowing => ["Code lines are growing", "Les lignes de code augmentent"],
+        Msg::MeasuredRate => [
+            "Over the last minute: {} code lines {}",
+            "Sur la dernière minute : {} lignes de code {}",
+        ],
+        Msg::DoubleCpsAdvice => [
+            "To double production: buy {} for {} code lines",
+            "Pour doubler la production : acheter {} pour {} lignes de code",
+        ],
+        Msg::ErrorLine => ["Error: {}", "Erreur : {}"],
+        Msg::RepeatedErrorLine => ["Error: {} (x{})", "Erreur : {} (x{})"],
+        Msg::StatusLine => [
+            "Lines: {} | Production: {} {} | Owned items: {} | {} | In {}s: {} | Mode: {}",
+            "Lignes : {} | Production : {} {} | Items possédés : {} | {} | Dans {} s : {} | Mode : {}",
+        ],
+        Msg::ClickTier => ["Coding tier {}: {} per press, {}", "Niveau de code {} : {} par appui, {}"],
+        Msg::NextClickTier => ["next for {} lines", "le suivant pour {} lignes"],
+        Msg::LastClickTier => ["last one", "le dernier"],
+        Msg::ModeNormal => ["normal", "normal"],
+        Msg::ModeBuy => ["buy", "achat"],
+        Msg::ModeSpinner => ["quantity", "quantité"],
+        Msg::ModeSell => ["sell", "vente"],
+        Msg::ModeSearch => ["search", "recherche"],
+        Msg::ModeConfirmReset => ["new game?", "recommencer ?"],
+        Msg::ModeConfirmPrestige => ["prestige?", "prestige ?"],
+        Msg::ModeShop => ["shop", "boutique"],
+        Msg::ModeFavorite => ["favorite", "favori"],
+        Msg::ModeCompare => ["compare", "comparaison"],
+        Msg::ModeConfirmBuy => ["buy?", "acheter ?"],
+        Msg::ModeConfirmUnlock => ["unlock?", "débloquer ?"],
+        Msg::ModeConfirmSell => ["sell?", "vendre ?"],
+        Msg::ModeImport => ["import", "import"],
+        Msg::ModeCommand => ["command", "commande"],
+        Msg::SearchTitle => ["Search", "Recherche"],
+        Msg::MatchesTitle => ["Matches", "Résultats"],
+        Msg::TerminalTooSmall => [
+            "Please resize your terminal to at least {}x{} (now {}x{}), q to quit",
+            "Merci d'agrandir le terminal à au moins {}x{} (actuellement {}x{}), q pour quitter",
+        ],
+        Msg::SummaryTitle => ["This session", "Cette session"],
+        Msg::SummaryLines => ["{} code lines produced", "{} lignes de code produites"],
+        Msg::SummaryPurchases => ["{} items bought", "{} items achetés"],
+        Msg::SummaryThresholds => ["{} thresholds reached", "{} paliers atteints"],
+        Msg::SummaryTime => ["Played for {} s", "{} s de jeu"],
+        Msg::SummaryAnyKey => ["Press any key to quit", "Appuyer sur une touche pour quitter"],
+        Msg::MatchLine => ["{} (as {})", "{} ({})"],
+        Msg::BoughtOne => [
+            "Bought 1 {} for {} lines (now own {})",
+            "Acheté 1 {} pour {} lignes (maintenant {} possédés)",
+        ],
+        Msg::BoughtMany => [
+            "Bought {} {} for {} lines, {} each (now own {})",
+            "Acheté {} {} pour {} lignes, {} chacun (maintenant {} possédés)",
+        ],
+        Msg::Sold => ["Sold {} {}", "Vendu {} {}"],
+        Msg::Undone => ["Cancelled buying {} {}", "Achat de {} {} annulé"],
+        Msg::GameReset => ["Started a new game", "Nouvelle partie"],
+        Msg::BudgetTruncated => [
+            "Only {} of the {} {} asked fit in the budget of {} lines",
+            "Seuls {} des {} {} demandés tiennent dans le budget de {} lignes",
+        ],
+        Msg::AutoCodeOn => [
+            "Auto-code on, a click of lines is written every second",
+            "Code automatique activé, un clic de lignes est écrit chaque seconde",
+        ],
+        Msg::AutoCodeOff => ["Auto-code off", "Code automatique désactivé"],
+        Msg::Prestiged => [
+            "Prestige! {} commits earned, {} to spend",
+            "Prestige ! {} commits gagnés, {} à dépenser",
+        ],
+        Msg::AutoPrestiged => [
+            "{} code lines reached, prestiging on its own",
+            "{} lignes de code atteintes, prestige automatique",
+        ],
+        Msg::UpgradePurchased => [
+            "Bought {}, production is now multiplied by {}",
+            "{} achetée, la production est maintenant multipliée par {}",
+        ],
+        Msg::SaveImported => ["Imported the save", "Sauvegarde importée"],
+        Msg::ItemVanished => [
+            "The {} {} of the save don't exist anymore, {} lines refunded",
+            "Les {} {} de la sauvegarde n'existent plus, {} lignes remboursées",
+        ],
+        Msg::UnknownItemDropped => [
+            "The save owns {} of an unknown item {}, dropped",
+            "La sauvegarde possède {} d'un item {} inconnu, abandonnés",
+        ],
+        Msg::ClickUpgraded => [
+            "Reached coding tier {}, {} lines per press",
+            "Niveau de code {} atteint, {} lignes par appui",
+        ],
+        Msg::NothingToSell => ["Nothing to sell", "Rien à vendre"],
+        Msg::CaughtUp => [
+            "Welcome back: {} s away, {} s of production credited",
+            "Bon retour : {} s d'absence, {} s de production créditées",
+        ],
+        Msg::ThresholdReached => [
+            "Congratulations, you own {} code lines!",
+            "Bravo, vous avez {} lignes de code !",
+        ],
+        Msg::UnrecognizedKey => ["Unrecognized key {}", "Touche non reconnue {}"],
+        Msg::FastForward => [
+            "Fast-forward: time runs {} times faster",
+            "Avance rapide : le temps passe {} fois plus vite",
+        ],
+        Msg::NormalSpeed => ["Back to normal speed", "Retour à la vitesse normale"],
+        Msg::InvariantsHold => ["Game state is consistent", "L'état du jeu est cohérent"],
+        Msg::InvariantBroken => [
+            "Game state is corrupted: {}",
+            "L'état du jeu est corrompu : {}",
+        ],
+        Msg::UnknownItem => ["no item is called {}", "aucun item ne s'appelle {}"],
+        Msg::DidYouMean => [", did you mean {}?", ", vouliez-vous dire {} ?"],
+        Msg::ItemMaxedOut => [
+            "you can't own more than {} {}",
+            "impossible de posséder plus de {} {}",
+        ],
+        Msg::NotEnoughLines => [
+            "you need more than {} code lines to buy {}, {} missing",
+            "il faut plus de {} lignes de code pour acheter {}, il en manque {}",
+        ],
+        Msg::ItemLocked => [
+            "{} must be unlocked first, for {} code lines",
+            "{} doit d'abord être débloqué, pour {} lignes de code",
+        ],
+        Msg::UnlockTooExpensive => [
+            "you need more than {} code lines to unlock {}, {} missing",
+            "il faut plus de {} lignes de code pour débloquer {}, il en manque {}",
+        ],
+        Msg::ReserveBreached => [
+            "buying {} for {} code lines would leave less than the {} lines of reserve",
+            "acheter {} pour {} lignes de code laisserait moins que la réserve de {} lignes",
+        ],
+        Msg::OverBudget => [
+            "one {} costs {} code lines, more than the budget of {} lines",
+            "un {} coûte {} lignes de code, plus que le budget de {} lignes",
+        ],
+        Msg::MacroStepFailed => ["step {} of the macro: {}", "étape {} de la macro : {}"],
+        Msg::MacroStepUnconfirmed => [
+            "step {} of the macro waits for a confirmation, the next ones were not done",
+            "l'étape {} de la macro attend une confirmation, les suivantes n'ont pas été faites",
+        ],
+        Msg::UnknownCommand => [
+            "unknown command {}, try buy, sell, code, upgrade, undo, pause or quit",
+            "commande inconnue {}, essayer buy, sell, code, upgrade, undo, pause ou quit",
+        ],
+        Msg::MissingItem => ["which item? type its name", "quel item ? taper son nom"],
+        Msg::InvalidQuantity => [
+            "{} isn't a quantity, type a count, next, all or max",
+            "{} n'est pas une quantité, taper un nombre, next, all ou max",
+        ],
+        Msg::NotOwned => [
+            "you own {} {}, not {}",
+            "vous possédez {} {}, pas {}",
+        ],
+        Msg::NoPurchaseToUndo => ["no purchase to undo", "aucun achat à annuler"],
+        Msg::NothingToPrestige => [
+            "a prestige needs at least {} code lines produced this game",
+            "un prestige demande au moins {} lignes de code produites dans la partie",
+        ],
+        Msg::UpgradeNotKnown => [
+            "no upgrade is called {}",
+            "aucune amélioration ne s'appelle {}",
+        ],
+        Msg::UpgradeAlreadyBought => ["{} is already bought", "{} est déjà achetée"],
+        Msg::NoFavorite => ["no item is a favorite yet", "aucun item n'est encore un favori"],
+        Msg::NotEnoughCommits => [
+            "{} costs {} commits, you only have {}",
+            "{} coûte {} commits, vous n'en avez que {}",
+        ],
+        Msg::NoClickTierLeft => [
+            "you already code as fast as possible",
+            "vous codez déjà le plus vite possible",
+        ],
+        Msg::LinesNotANumber => [
+            "code lines are not a number anymore, nothing can be bought",
+            "les lignes de code ne sont plus un nombre, impossible d'acheter",
+        ],
+        Msg::ImportInvalid => [
+            "this is not an exported save: {}",
+            "ce n'est pas une sauvegarde exportée : {}",
+        ],
+        Msg::ProductionNotFinite => [
+            "the items produce {} lines per second, production stopped until it's fixed",
+            "les items produisent {} lignes par seconde, production arrêtée en attendant",
+        ],
+        Msg::LinesNotFinite => [
+            "code lines became {}, they were brought back to a number",
+            "les lignes de code valaient {}, elles ont été ramenées à un nombre",
+        ],
+        Msg::KeyConflict => [
+            "the key {} is bound to both {} and {}",
+            "la touche {} est attribuée à la fois à {} et à {}",
+        ],
+        Msg::ForbiddenInHardcore => [
+            "no selling nor undoing in hardcore mode",
+            "ni vente ni annulation en mode hardcore",
+        ],
+        Msg::ForbiddenInIronman => [
+            "no exporting nor importing in ironman mode",
+            "ni export ni import en mode ironman",
+        ],
+        Msg::ClickTierTooExpensive => [
+            "you need more than {} code lines to code faster, {} missing",
+            "il faut plus de {} lignes de code pour coder plus vite, il en manque {}",
+        ],
+        Msg::CannotRead => ["could not read {}: {}", "impossible de lire {} : {}"],
+        Msg::FileMalformed => [
+            "{} is malformed at line {} column {}: {}",
+            "{} est mal formé ligne {} colonne {} : {}",
+        ],
+        Msg::SynergyItemMissing => [
+            "a synergy between items {} and {} refers to an item that doesn't exist",
+            "une synergie entre les items {} et {} désigne un item qui n'existe pas",
+        ],
+        Msg::UnknownArgument => ["unknown argument {}", "argument inconnu {}"],
+        Msg::ArgumentNeedsValue => ["{} needs a value", "{} attend une valeur"],
+        Msg::SaveFailed => [
+            "could not save to {}, previous save kept: {}",
+            "impossible de sauvegarder dans {}, l'ancienne sauvegarde est gardée : {}",
+        ],
+        Msg::SaveLocked => [
+            "another game is using {}, the last one to quit will overwrite the other",
+            "une autre partie utilise {}, la dernière à quitter écrasera l'autre",
+        ],
+        Msg::SaveLockedReadOnly => [
+            "another game is using {}, this one won't be saved",
+            "une autre partie utilise {}, celle-ci ne sera pas sauvegardée",
+        ],
+        Msg::ReplayDiffers => [
+            "the replay didn't end like the recorded game",
+            "le jeu rejoué ne finit pas comme la partie enregistrée",
+        ],
+    };
+    match lang {
+        Lang::English => english,
+        Lang::French => french,
+    }
+}
+
+/// The sentence `msg` in `lang`, with its placeholders replaced by `args`.
+pub fn tf(msg: Msg, lang: Lang, args: &[&dyn std::fmt::Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = t(msg, lang).split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_message_resolves_in_both_languages() {
+        for &msg in Msg::ALL {
+            let english = t(msg, Lang::English);
+            let french = t(msg, Lang::French);
+            assert!(!english.trim().is_empty(), "{msg:?} is empty in english");
+            assert!(!french.trim().is_empty(), "{msg:?} is empty in french");
+            assert_eq!(
+                english.matches("{}").count(),
+                french.matches("{}").count(),
+                "{msg:?} has different placeholders"
+            );
+        }
+    }
+
+    #[test]
+    fn tf_fills_placeholders_in_order() {
+        assert_eq!(
+            tf(Msg::KeyConflict, Lang::English, &[&'x', &"buy", &"sell"]),
+            "the key x is bound to both buy and sell"
+        );
+        assert_eq!(
+            tf(Msg::UnknownArgument, Lang::French, &[&"--nope"]),
+            "argument inconnu --nope"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ClidleError;
+
+/// The keys of the normal mode actions, to adapt the game to another keyboard.
+///
+/// Saved with the game, so a shared save keeps the controls it was played with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub code: char,
+    pub buy: char,
+    pub sell: char,
+    pub sell_worst: char,
+    pub quit: char,
+    pub search: char,
+    pub undo: char,
+    pub rebuy: char,
+    pub reset: char,
+    pub prestige: char,
+    pub shop: char,
+    pub upgrade_click: char,
+    pub pause: char,
+    pub auto_code: char,
+    pub layout: char,
+    pub focus: char,
+    pub import: char,
+    pub command: char,
+    pub language: char,
+    pub favorite: char,
+    pub favorites_only: char,
+    pub buy_favorites: char,
+    pub compare: char,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            code: 'c',
+            buy: 'b',
+            sell: 's',
+            sell_worst: 'w',
+            quit: 'q',
+            search: '/',
+            undo: 'u',
+            rebuy: 'r',
+            reset: 'n',
+            prestige: 'P',
+            shop: 'o',
+            upgrade_click: 'k',
+            pause: 'p',
+            auto_code: 'a',
+            layout: 'v',
+            focus: 'f',
+            import: 'i',
+            command: ':',
+            language: 'l',
+            favorite: 'm',
+            favorites_only: 'M',
+            buy_favorites: 'B',
+            compare: 'x',
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with its key, the forecast keys `+` and `-` included
+    /// even though they can't be changed.
+    fn actions(&self) -> [(&'static str, char); 25] {
+        [
+            ("code", self.code),
+            ("buy", self.buy),
+            ("sell", self.sell),
+            ("sell_worst", self.sell_worst),
+            ("quit", self.quit),
+            ("search", self.search),
+            ("undo", self.undo),
+            ("rebuy", self.rebuy),
+            ("reset", self.reset),
+            ("prestige", self.prestige),
+            ("shop", self.shop),
+            ("upgrade_click", self.upgrade_click),
+            ("pause", self.pause),
+            ("auto_code", self.auto_code),
+            ("layout", self.layout),
+            ("focus", self.focus),
+            ("import", self.import),
+            ("command", self.command),
+            ("language", self.language),
+            ("favorite", self.favorite),
+            ("favorites_only", self.favorites_only),
+            ("buy_favorites", self.buy_favorites),
+            ("compare", self.compare),
+            ("longer_forecast", '+'),
+            ("shorter_forecast", '-'),
+        ]
+    }
+
+    /// Check that no key is bound to two actions.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::KeyConflict` with the first key bound twice.
+    pub fn check(&self) -> Result<(), ClidleError> {
+        let actions = self.actions();
+        for (index, (action, key)) in actions.iter().enumerate() {
+            if let Some((other, _)) = actions[index + 1..].iter().find(|(_, k)| k == key) {
+                return Err(ClidleError::KeyConflict(
+                    *key,
+                    action.to_string(),
+                    other.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Affordability;
+
+/// An amount of code lines.
+///
+/// Production gives fractional lines while prices are whole lines: comparing
+/// both always goes through `can_pay`, so they can't be mixed up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lines(pub f64);
+
+impl Lines {
+    pub const ZERO: Lines = Lines(0.);
+
+    /// A whole number of lines, like a price.
+    pub fn from_u64(lines: u64) -> Lines {
+        Lines(lines as f64)
+    }
+
+    /// Only the whole lines.
+    pub fn floor(self) -> Lines {
+        Lines(self.0.floor())
+    }
+
+    /// The whole lines as an integer, 0 if negative.
+    pub fn as_u64(self) -> u64 {
+        // `as` sature : les négatifs donnent 0 et les trop grands u64::MAX
+        self.floor().0 as u64
+    }
+
+    /// The lines that count to pay a price: the whole ones, or all of them
+    /// rounded up with `Affordability::Exact`.
+    pub fn spendable(self, affordability: Affordability) -> u64 {
+        match affordability {
+            Affordability::Floored => self.as_u64(),
+            // `as` sature comme pour `as_u64`
+            Affordability::Exact => self.0.ceil() as u64,
+        }
+    }
+
+    /// Whether these lines are enough to pay `price`.
+    ///
+    /// You need strictly more lines than the price, only counting the whole ones
+    /// unless `affordability` is `Exact`. Free things are always affordable.
+    ///
+    /// Compared as floats, so lines above `u64::MAX` still pay for anything,
+    /// while negative lines and NaN pay for nothing.
+    pub fn can_pay(self, price: u64, affordability: Affordability) -> bool {
+        let lines = match affordability {
+            Affordability::Floored => self.0.floor(),
+            Affordability::Exact => self.0,
+        };
+        price == 0 || (price as f64) < lines
+    }
+
+    /// How many whole lines are missing to pay `price`, 0 if you `can_pay` it.
+    pub fn missing_for(self, price: u64, affordability: Affordability) -> u64 {
+        if self.can_pay(price, affordability) {
+            return 0;
+        }
+        match affordability {
+            Affordability::Floored => price.saturating_add(1).saturating_sub(self.as_u64()),
+            // il faut dépasser le prix, même d'une fraction de ligne
+            Affordability::Exact => (price as f64 - self.0).floor() as u64 + 1,
+        }
+    }
+
+    pub fn max(self, other: Lines) -> Lines {
+        Lines(self.0.max(other.0))
+    }
+}
+
+impl Add for Lines {
+    type Output = Lines;
+
+    fn add(self, rhs: Lines) -> Lines {
+        Lines(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Lines {
+    type Output = Lines;
+
+    fn sub(self, rhs: Lines) -> Lines {
+        Lines(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Lines {
+    fn add_assign(&mut self, rhs: Lines) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Lines {
+    fn sub_assign(&mut self, rhs: Lines) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Lines {
+    type Output = Lines;
+
+    fn neg(self) -> Lines {
+        Lines(-self.0)
+    }
+}
+
+/// Formats like a `f64`, so `{:.2}` works.
+impl fmt::Display for Lines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Format a number of lines with `places` decimals, as chosen in the config.
+pub fn format_lines(value: f64, places: usize) -> String {
+    format!("{value:.places$}")
+}
+
+/// Format a number of lines to stay readable even when it's huge.
+///
+/// Up to a million lines it's shown with `places` decimals, scientific notation after.
+pub fn format_large(value: f64, places: usize) -> String {
+    if value.abs() < 1e6 {
+        format_lines(value, places)
+    } else {
+        format!("{value:.3e}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let mut lines = Lines(10.5) + Lines(2.) - Lines(0.5);
+        assert_eq!(lines, Lines(12.));
+        lines += Lines(3.);
+        lines -= Lines::from_u64(5);
+        assert_eq!(lines, Lines(10.));
+        assert_eq!(-lines, Lines(-10.));
+        assert_eq!(Lines(-1.).max(Lines::ZERO), Lines::ZERO);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Lines(49.9).floor(), Lines(49.));
+        assert_eq!(Lines(49.9).as_u64(), 49);
+        assert_eq!(Lines(-3.).as_u64(), 0);
+        assert_eq!(Lines(f64::MAX).as_u64(), u64::MAX);
+        assert_eq!(Lines(49.9).spendable(Affordability::Floored), 49);
+        assert_eq!(Lines(49.9).spendable(Affordability::Exact), 50);
+        assert_eq!(format!("{:.1}", Lines(2.25)), "2.2");
+    }
+
+    #[test]
+    fn paying_needs_more_lines_than_the_price() {
+        let floored = Affordability::Floored;
+        assert!(!Lines(10.).can_pay(10, floored));
+        assert!(Lines(11.).can_pay(10, floored));
+        assert!(!Lines(10.9).can_pay(10, floored));
+        assert!(Lines(10.9).can_pay(10, Affordability::Exact));
+        assert!(Lines(0.).can_pay(0, floored));
+        assert!(!Lines(f64::NAN).can_pay(1, floored));
+        assert_eq!(Lines(7.5).missing_for(10, floored), 4);
+        assert_eq!(Lines(7.5).missing_for(10, Affordability::Exact), 3);
+        assert_eq!(Lines(20.).missing_for(10, floored), 0);
+    }
+
+    #[test]
+    fn large_numbers_stay_readable() {
+        assert_eq!(format_lines(2.468, 2), "2.47");
+        assert_eq!(format_large(999_999., 0), "999999");
+        assert_eq!(format_large(1_234_567., 2), "1.235e6");
+    }
+
+    #[test]
+    fn decimal_places() {
+        assert_eq!(format_lines(1234.56789, 0), "1235");
+        assert_eq!(format_lines(1234.56789, 2), "1234.57");
+        assert_eq!(format_lines(1234.56789, 4), "1234.5679");
+        assert_eq!(format_large(0.5, 0), "0");
+        assert_eq!(format_large(0.5, 4), "0.5000");
+    }
+}
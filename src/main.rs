@@ -1,26 +1,130 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{self},
     fs,
+    rc::Rc,
     time::{Duration, Instant},
 };
 
-use crossterm::{
-    event::{self, poll, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use serde::{Deserialize, Serialize};
 use std::{error::Error, io};
 use tui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Sparkline, Wrap},
     Frame, Terminal,
 };
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use cli::Args;
+use clock::{Clock, ManualClock, SystemClock};
+use config::{
+    Config, HelpVerbosity, PrestigeUpgrade, RateUnit, StartMode, UiLayout, Vanished, CONFIG_PATH,
+};
+use debug::DebugInfo;
+use history::{GainWindow, Purchase, PurchaseHistory, Samples};
+use i18n::{t, tf, Lang, Msg};
+use keys::KeyBindings;
+use lines::{format_large, format_lines, Lines};
+use replay::{key_name, RecordedEvent, Recording};
+use rng::Rng;
+use save::{Save, SaveLock, SavedItem, SAVE_PATH};
+use stats::SessionStats;
+use term::Input;
+
+mod base64;
+mod cli;
+mod clock;
+mod config;
+mod debug;
+mod history;
+mod i18n;
+mod keys;
+mod lines;
+mod replay;
+mod rng;
+mod save;
+mod stats;
+mod term;
+mod theme;
+
+/// The items owned in a new game, from their names in the config.
+///
+/// ## Errors
+///
+/// `ClidleError::ConfigMalformed` if a starting item doesn't exist,
+/// or if there are more of it than its `max`.
+fn starting_items(config: &Config, items: &[Item]) -> Result<HashMap<ItemId, f64>, ClidleError> {
+    let mut owned_items = HashMap::new();
+    for (name, count) in config.starting_items.iter().filter(|(_, c)| **c > 0) {
+        let item = items.iter().find(|i| &i.name == name).ok_or_else(|| {
+            ClidleError::ConfigMalformed(format!("no starting item is called {name}"))
+        })?;
+        if let Some(max) = item.max.filter(|max| count > max) {
+            return Err(ClidleError::ConfigMalformed(format!(
+                "starting with {count} {name}, at most {max} are allowed"
+            )));
+        }
+        owned_items.insert(item.id, *count as f64);
+    }
+    Ok(owned_items)
+}
+
+/// How many owned counts are kept for the chart of each item, see `Samples`.
+const OWNED_SAMPLES: usize = 60;
+
+/// How far back the measured production looks, see `GainWindow`.
+const GAIN_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long the help line flashes after the code lines changed sharply.
+const FLASH_DURATION: Duration = Duration::from_millis(200);
+
+/// Share of the code lines that must be won or lost at once to flash.
+const FLASH_RATIO: f64 = 0.25;
+
+/// Fewest code lines won or lost at once to flash, so the first lines don't.
+const FLASH_MIN_LINES: f64 = 10.;
+
+/// How long the help line stays highlighted after reaching a threshold.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+
+/// A tick late by more than this means the game was suspended, the time away then
+/// only counts for `offline_rate`. A slow frame or a busy machine is credited in full.
+const SUSPEND_SECS: u64 = 10;
+
+/// How much faster time runs in fast-forward, see `App::time_scale`.
+const FAST_FORWARD: f64 = 10.;
+
+/// Where the items are read from by default.
+const ITEMS_PATH: &str = "items.json";
+
+/// Read the item catalog from the file `path`, or from `stdin` if `path` is `-`.
+///
+/// ## Errors
+///
+/// `ClidleError::ItemsUnreadable` if reading fails.
+fn read_catalog(path: &str, mut stdin: impl io::Read) -> Result<String, ClidleError> {
+    let mut catalog = String::new();
+    let res = if path == "-" {
+        stdin.read_to_string(&mut catalog).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|content| catalog = content)
+    };
+    res.map_err(|e| ClidleError::ItemsUnreadable(path.to_string(), e.to_string()))?;
+    Ok(catalog)
+}
+
+/// Identifiant d'un item, son index dans `App::items_index`.
+///
+/// Un type dédié évite de confondre un identifiant avec un nombre d'items.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+struct ItemId(usize);
 
 /// Un item pour produire du code
 ///
@@ -42,18 +146,110 @@ struct Item {
     cost: u64,
     /// identifiant unique
     #[serde(default)]
-    id: usize,
+    id: ItemId,
     /// nom pour interagir avec l'item
     name: String,
     /// nom complet à afficher de l'item
     long_name: String,
+    /// nombre maximum d'exemplaires possédés, sans limite si absent
+    #[serde(default)]
+    max: Option<u64>,
+    /// gabarit d'affichage dans la liste, voir `fill_template`
+    #[serde(default)]
+    display_template: Option<String>,
+    /// emoji ou symbole affiché devant l'item dans la liste
+    #[serde(default)]
+    icon: Option<String>,
+    /// écart relatif de la production à chaque tick, 0.2 pour ±20 %, voir `Rng::jitter`
+    #[serde(default)]
+    cps_variance: f64,
+    /// texte d'ambiance affiché quand l'item est sélectionné, rien si vide
+    #[serde(default)]
+    description: String,
+    /// prix payé une seule fois avant de pouvoir l'acheter, voir `unlock`
+    #[serde(default)]
+    unlock_cost: Option<u64>,
+}
+
+/// Owning some of an item makes another one more productive.
+#[derive(Debug, Deserialize)]
+struct Synergy {
+    /// the item you own
+    source_id: ItemId,
+    /// the item boosted
+    target_id: ItemId,
+    /// how much each owned source adds to the target cps, 0.01 for 1%
+    per_unit_bonus: f64,
+}
+
+/// Content of `items.json`, see `parse_catalog`.
+#[derive(Deserialize)]
+struct Catalog {
+    items: Vec<Item>,
+    #[serde(default)]
+    synergies: Vec<Synergy>,
+}
+
+/// Parse `items.json`: either just the list of items, or the items and their synergies.
+///
+/// Which one is told by the first character, so that an error points at the faulty
+/// item field rather than at none of the two matching.
+fn parse_catalog(json: &str) -> serde_json::Result<Catalog> {
+    if json.trim_start().starts_with('[') {
+        Ok(Catalog {
+            items: serde_json::from_str(json)?,
+            synergies: Vec::new(),
+        })
+    } else {
+        serde_json::from_str(json)
+    }
 }
 
 /// Les input auront des effets différents selon
 /// dans quel mode on se situe
 enum InputMode {
     Buy,
+    /// choosing with `+` and `-` how many of `item` to buy, `buy_quantity`
+    Spinner {
+        item: String,
+    },
     Sell,
+    /// picking an item to buy in the search overlay
+    Search,
+    /// typing a save exported with `--export`
+    Import,
+    /// typing actions as commands, see `parse_action`
+    Command,
+    /// waiting for the player to confirm starting a new game
+    ConfirmReset,
+    /// waiting for the player to confirm a prestige
+    ConfirmPrestige,
+    /// typing a prestige upgrade to buy with commits
+    Shop,
+    /// typing an item to mark or unmark as favorite
+    Favorite,
+    /// typing items to compare side by side, see `App::compared`
+    Compare,
+    /// waiting for the player to confirm paying the `unlock_cost` of `item`,
+    /// then going `back_to` the mode it was asked from
+    ConfirmUnlock {
+        item: String,
+        cost: u64,
+        back_to: Box<InputMode>,
+    },
+    /// waiting for the player to confirm a purchase above `confirm_above`
+    ConfirmBuy {
+        item: String,
+        count: f64,
+        price: u64,
+        back_to: Box<InputMode>,
+    },
+    /// waiting for the player to confirm selling the last copies, with `confirm_last_sell`
+    ConfirmSell {
+        item: String,
+        count: f64,
+        back_to: Box<InputMode>,
+    },
     Normal,
 }
 
@@ -61,80 +257,945 @@ enum InputMode {
 struct App {
     /// Current value of the input box
     input: String,
+    /// What is typed in the search overlay
+    search_query: String,
+    /// page of the item list, starting at 0
+    page: usize,
+    /// how far ahead the forecast looks, in seconds
+    forecast_secs: u64,
     /// Current input mode
     input_mode: InputMode,
-    /// Items you bought (item_id, item count)
-    owned_items: HashMap<usize, u64>,
+    /// Items you bought (item_id, item count), whole counts unless `fractional_items`
+    owned_items: HashMap<ItemId, f64>,
     /// total owned code lines
-    code_lines: f64,
+    code_lines: Lines,
     /// available items: index is item id
     items_index: Vec<Item>,
+    /// bonuses between items, from the catalog
+    synergies: Vec<Synergy>,
+    /// items bought since the beginning of the game, selling doesn't lower it
+    lifetime_purchases: u64,
+    /// highest production per second of this game, kept in the save
+    peak_cps: f64,
+    /// code lines produced during this game, kept in the save
+    lifetime_lines: Lines,
+    /// seconds of production during this game, pauses excluded
+    played_secs: u64,
+    /// whether a non finite production or lines count was already logged
+    reported_not_finite: bool,
+    /// frames drawn since the start, to animate the spinner
+    frame: u64,
+    /// how many click tiers from the config were bought
+    click_tier: usize,
+    /// prestige currency left to spend, kept through prestiges
+    commits: u64,
+    /// names of the prestige upgrades bought, kept through prestiges
+    prestige_upgrades: Vec<String>,
+    /// items marked by the player, kept through new games
+    favorites: HashSet<ItemId>,
+    /// whether the item list only shows the favorites
+    favorites_only: bool,
+    /// items whose `unlock_cost` was paid in this game
+    unlocked_items: HashSet<ItemId>,
+    /// the two items of the comparison, the oldest first
+    compared: [Option<ItemId>; 2],
+    /// highest threshold from the config the code lines went over
+    reached_threshold: u64,
+    /// some while the help line is highlighted after reaching a threshold
+    highlight_until: Option<Instant>,
+    /// the terminal bell should ring after the next frame, with `bell` in the config,
+    /// for a threshold, a prestige or an upgrade
+    ring: bool,
+    /// code lines when the game loop last looked, to notice sharp changes
+    last_balance: Lines,
+    /// some while the help line flashes after a sharp change, and until when
+    flash: Option<(Flash, Instant)>,
+    /// whether items stopped producing, toggled with `p`
+    paused: bool,
+    /// whether a click of lines is written every second without pressing `c`
+    auto_code: bool,
+    /// keys of the normal mode actions, from the save or the config
+    keys: KeyBindings,
+    /// game seconds in each real second, sped up with F10 in `--debug`
+    time_scale: f64,
+    /// how the screen is split, toggled with `v`
+    layout: UiLayout,
+    /// whether only the counter is shown, toggled with `f`
+    focus_mode: bool,
+    /// latest purchases, to undo or repeat them
+    history: PurchaseHistory,
+    /// owned count of each item over this session, index is item id
+    owned_samples: Vec<Samples>,
+    /// draws the production noise of items with a `cps_variance`
+    rng: Rng,
+    /// code lines gained over the last `GAIN_WINDOW`, for the measured production
+    gains: GainWindow,
+    /// how many items the spinner buys, see `InputMode::Spinner`
+    buy_quantity: u64,
+    /// where every change to the game is saved right away, with `ironman`
+    autosave: Option<String>,
+    /// what `autosave` last wrote, so an action changing nothing doesn't write again
+    autosaved: Option<Save>,
+    /// code lines shown by the counters, rolling toward `code_lines` with `smooth_counter`
+    shown_lines: Lines,
     /// some if an error occurred
     error: Result<(), ClidleError>,
+    /// how many times in a row `error` occurred
+    error_repeats: usize,
+    /// what happened lately, most recent last
+    log: Vec<String>,
+    /// language of the ui
+    lang: Lang,
+    /// where the time is read, the real one but in replays
+    clock: Rc<dyn Clock>,
+    /// player settings
+    config: Config,
+    /// counters for this session only
+    stats: SessionStats,
+    /// game loop timings, only with `--debug`
+    debug: Option<DebugInfo>,
+    /// every event of the game, only with `--record`
+    recording: Option<Recording>,
+    /// when the player last pressed a key, for `idle_quit_secs`
+    last_activity: Instant,
+    /// something changed since the last frame was drawn
+    dirty: bool,
+    /// `double_cps_advice` as of the last tick, too slow to simulate at every frame
+    advice: Option<Advice>,
+    /// `outlook` when last checked, to log it only when it changes
+    outlook: Outlook,
 }
 
 impl App {
-    fn new() -> App {
-        let mut items_index: Vec<Item> = serde_json::from_str(
-            &fs::read_to_string("items.json").expect("Should have been able to read the file"),
-        )
-        .unwrap();
+    /// A fresh game, starting with the lines and items from the config.
+    ///
+    /// The items are read from `items_path`, see `read_catalog`.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::ItemsUnreadable` if the items can't be read,
+    /// `ClidleError::ItemsFileMalformed` if they aren't valid items, or
+    /// `ClidleError::ConfigMalformed` if a starting item doesn't exist or is over its `max`.
+    fn new(config: Config, items_path: &str) -> Result<App, ClidleError> {
+        let json = read_catalog(items_path, io::stdin().lock())?;
+        App::with_catalog(config, &json, items_path)
+    }
+
+    /// A fresh game with the items of `json`, read from `items_path`, see `App::new`.
+    ///
+    /// ## Errors
+    ///
+    /// The errors of `App::new`, but `ClidleError::ItemsUnreadable`.
+    fn with_catalog(mut config: Config, json: &str, items_path: &str) -> Result<App, ClidleError> {
+        let Catalog {
+            items: mut items_index,
+            synergies,
+        } = parse_catalog(json).map_err(|e| {
+            // serde_json finit son message par la position, gardée à part
+            let message = e.to_string();
+            let reason = match message.rsplit_once(" at line ") {
+                Some((reason, _)) => reason.to_string(),
+                None => message,
+            };
+            ClidleError::ItemsFileMalformed(items_path.to_string(), e.line(), e.column(), reason)
+        })?;
+        if let Some(synergy) = synergies.iter().find(|synergy| {
+            synergy.source_id.0 >= items_index.len() || synergy.target_id.0 >= items_index.len()
+        }) {
+            return Err(ClidleError::SynergyItemMissing(
+                synergy.source_id.0,
+                synergy.target_id.0,
+            ));
+        }
         // set ids
         items_index
             .iter_mut()
             .enumerate()
-            .for_each(|(id, item)| item.id = id);
-        App {
+            .for_each(|(id, item)| item.id = ItemId(id));
+
+        let owned_items = starting_items(&config, &items_index)?;
+        let owned_samples = vec![Samples::new(OWNED_SAMPLES); items_index.len()];
+        // la graine tirée est gardée dans la config pour qu'un enregistrement la rejoue
+        let seed = *config.seed.get_or_insert_with(Rng::clock_seed);
+        let clock = Rc::new(SystemClock);
+        let now = clock.now();
+
+        let mut app = App {
             input: String::new(),
-            input_mode: InputMode::Normal,
-            owned_items: HashMap::new(),
-            code_lines: 0.,
+            search_query: String::new(),
+            page: 0,
+            forecast_secs: config.forecast_secs.clamp(1, MAX_FORECAST_SECS),
+            input_mode: match config.start_mode {
+                StartMode::Normal => InputMode::Normal,
+                StartMode::Buy => InputMode::Buy,
+                StartMode::Command => InputMode::Command,
+            },
+            owned_items,
+            code_lines: config.starting_lines,
             items_index,
+            synergies,
+            lifetime_purchases: 0,
+            peak_cps: 0.,
+            lifetime_lines: Lines::ZERO,
+            played_secs: 0,
+            reported_not_finite: false,
+            frame: 0,
+            click_tier: 0,
+            commits: 0,
+            prestige_upgrades: Vec::new(),
+            favorites: HashSet::new(),
+            favorites_only: false,
+            unlocked_items: HashSet::new(),
+            compared: [None; 2],
+            reached_threshold: 0,
+            highlight_until: None,
+            ring: false,
+            last_balance: config.starting_lines,
+            shown_lines: config.starting_lines,
+            autosave: None,
+            autosaved: None,
+            buy_quantity: 1,
+            flash: None,
+            paused: config.start_paused,
+            auto_code: config.auto_code,
+            keys: config.keys.clone(),
+            time_scale: 1.,
+            layout: config.layout,
+            focus_mode: false,
+            history: PurchaseHistory::new(config.history_depth),
+            owned_samples,
+            rng: Rng::new(seed),
             error: Ok(()),
+            error_repeats: 0,
+            log: Vec::new(),
+            lang: config.language,
+            config,
+            stats: SessionStats::new(now),
+            debug: None,
+            recording: None,
+            clock,
+            gains: GainWindow::new(GAIN_WINDOW, now),
+            last_activity: now,
+            dirty: true,
+            advice: None,
+            outlook: Outlook::Stable,
+        };
+        // des lignes de départ ne méritent pas de félicitations
+        app.reached_threshold = app.threshold_below(app.code_lines);
+        Ok(app)
+    }
+
+    /// Start over from the starting lines and items of the config, without quitting.
+    ///
+    /// Unlike a prestige nothing is kept nor granted.
+    fn reset(&mut self) {
+        self.start_over();
+        self.commits = 0;
+        self.prestige_upgrades.clear();
+        self.log.push(t(Msg::GameReset, self.lang).to_string());
+    }
+
+    /// Start over for the commits earned in this game, see `prestige_commits`.
+    ///
+    /// Commits and prestige upgrades are kept, everything else starts over.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::NothingToPrestige` if it wouldn't earn any commit.
+    fn prestige(&mut self) -> Result<(), ClidleError> {
+        let earned = prestige_commits(self.lifetime_lines, self.config.prestige_lines);
+        if earned == 0 {
+            return Err(ClidleError::NothingToPrestige(self.config.prestige_lines));
+        }
+        self.start_over();
+        self.commits += earned;
+        self.log
+            .push(tf(Msg::Prestiged, self.lang, &[&earned, &self.commits]));
+        self.ring = self.config.bell;
+        Ok(())
+    }
+
+    /// Everything a new game and a prestige both start from again.
+    fn start_over(&mut self) {
+        self.code_lines = self.config.starting_lines;
+        self.last_balance = self.code_lines;
+        // déjà vérifiés par App::new
+        self.owned_items = starting_items(&self.config, &self.items_index).unwrap_or_default();
+        self.lifetime_purchases = 0;
+        self.peak_cps = 0.;
+        self.lifetime_lines = Lines::ZERO;
+        self.played_secs = 0;
+        self.click_tier = 0;
+        self.reached_threshold = self.threshold_below(self.code_lines);
+        self.history = PurchaseHistory::new(self.config.history_depth);
+        self.owned_samples = vec![Samples::new(OWNED_SAMPLES); self.items_index.len()];
+        self.unlocked_items.clear();
+        self.gains = GainWindow::new(GAIN_WINDOW, self.clock.now());
+        self.error = Ok(());
+    }
+
+    /// Keep the result of the last action, counting the same error repeated in a row
+    /// instead of showing it again.
+    fn report(&mut self, result: Result<(), ClidleError>) {
+        // les erreurs se comparent par leur message, elles n'ont pas besoin d'être égales
+        let repeated = match (&result, &self.error) {
+            (Err(new), Err(last)) => new.to_string() == last.to_string(),
+            _ => false,
+        };
+        self.error_repeats = if repeated { self.error_repeats + 1 } else { 1 };
+        self.error = result;
+    }
+
+    /// Production multiplier of the prestige upgrades bought.
+    fn prestige_multiplier(&self) -> f64 {
+        self.config
+            .prestige_upgrades
+            .iter()
+            .filter(|upgrade| self.prestige_upgrades.contains(&upgrade.name))
+            .map(|upgrade| upgrade.cps_multiplier)
+            .product()
+    }
+
+    /// Pick up a saved game where it was left.
+    ///
+    /// If `items.json` changed since the save, owned items are found again by name,
+    /// see `App::migrate`.
+    fn restore(&mut self, save: Save) {
+        self.code_lines = save.code_lines;
+        // reprendre une partie n'est pas un gain
+        self.last_balance = save.code_lines;
+        self.shown_lines = save.code_lines;
+        self.owned_items = if save.catalog.is_empty() {
+            self.known_items(save.owned_items)
+        } else if save.catalog == self.catalog() {
+            save.owned_items
+        } else {
+            self.migrate(&save.catalog, save.owned_items)
+        };
+        self.lifetime_purchases = save.lifetime_purchases;
+        self.peak_cps = save.peak_cps;
+        self.lifetime_lines = save.lifetime_lines;
+        self.played_secs = save.played_secs;
+        self.click_tier = save.click_tier.min(self.config.click_tiers.len());
+        self.commits = save.commits;
+        self.prestige_upgrades = save.prestige_upgrades;
+        // par nom, pour survivre à un items.json modifié
+        self.favorites = save
+            .favorites
+            .iter()
+            .filter_map(|name| self.items_index.iter().find(|item| item.name == *name))
+            .map(|item| item.id)
+            .collect();
+        self.unlocked_items = save
+            .unlocked_items
+            .iter()
+            .filter_map(|name| self.items_index.iter().find(|item| item.name == *name))
+            .map(|item| item.id)
+            .collect();
+        self.reached_threshold = self.threshold_below(self.code_lines);
+        // les gains mesurés étaient ceux de la partie remplacée
+        self.gains = GainWindow::new(GAIN_WINDOW, self.clock.now());
+        // des touches en conflit ne rendent pas la partie illisible, on garde les précédentes
+        if let Some(keys) = save.keys {
+            match keys.check() {
+                Ok(()) => self.keys = keys,
+                Err(error) => self.log.push(tf(
+                    Msg::ErrorLine,
+                    self.lang,
+                    &[&error.localized(self.lang)],
+                )),
+            }
+        }
+    }
+
+    /// Save the game to `path`, keeping the previous save if it fails.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::SaveFailed` if the save could not be written.
+    fn save(&self, path: &str) -> Result<(), ClidleError> {
+        self.snapshot()
+            .write(path)
+            .map_err(|e| ClidleError::SaveFailed(e.to_string()))
+    }
+
+    /// The items as remembered by a save.
+    fn catalog(&self) -> Vec<SavedItem> {
+        self.items_index
+            .iter()
+            .map(|item| SavedItem {
+                name: item.name.clone(),
+                cost: item.cost,
+            })
+            .collect()
+    }
+
+    /// Owned items of a save made with another `catalog`, with the ids of the current one.
+    ///
+    /// Items are matched by name. Those that disappeared are reported in the log and
+    /// refunded like a sale, or dropped with `"vanished_items": "drop"` in the config.
+    fn migrate(
+        &mut self,
+        catalog: &[SavedItem],
+        owned: HashMap<ItemId, f64>,
+    ) -> HashMap<ItemId, f64> {
+        let mut migrated = HashMap::new();
+        for (old_id, count) in owned {
+            let Some(saved) = catalog.get(old_id.0) else {
+                continue;
+            };
+            match self.items_index.iter().find(|item| item.name == saved.name) {
+                Some(item) => *migrated.entry(item.id).or_insert(0.) += count,
+                None => {
+                    let refund = match self.config.vanished_items {
+                        Vanished::Refund => price_for(saved.cost, count) / 2,
+                        Vanished::Drop => 0,
+                    };
+                    self.code_lines += Lines::from_u64(refund);
+                    self.log.push(tf(
+                        Msg::ItemVanished,
+                        self.lang,
+                        &[&format_count(count), &saved.name, &refund],
+                    ));
+                }
+            }
+        }
+        migrated
+    }
+
+    /// Owned items of a save without catalog, whose ids must be the current ones.
+    ///
+    /// Ids past the end of the catalog are reported in the log and dropped.
+    fn known_items(&mut self, owned: HashMap<ItemId, f64>) -> HashMap<ItemId, f64> {
+        let (known, unknown): (HashMap<_, _>, HashMap<_, _>) = owned
+            .into_iter()
+            .partition(|(item_id, _)| item_id.0 < self.items_index.len());
+        for (item_id, count) in unknown {
+            self.log.push(tf(
+                Msg::UnknownItemDropped,
+                self.lang,
+                &[&format_count(count), &item_id.0],
+            ));
+        }
+        known
+    }
+
+    /// What would be saved right now.
+    fn snapshot(&self) -> Save {
+        Save {
+            code_lines: self.code_lines,
+            owned_items: self.owned_items.clone(),
+            lifetime_purchases: self.lifetime_purchases,
+            peak_cps: self.peak_cps,
+            lifetime_lines: self.lifetime_lines,
+            played_secs: self.played_secs,
+            click_tier: self.click_tier,
+            catalog: self.catalog(),
+            commits: self.commits,
+            prestige_upgrades: self.prestige_upgrades.clone(),
+            // les touches de la configuration ne sont pas figées dans la sauvegarde,
+            // pour qu'en changer la configuration s'applique encore
+            keys: (self.keys != self.config.keys).then(|| self.keys.clone()),
+            favorites: self
+                .items_index
+                .iter()
+                .filter(|item| self.favorites.contains(&item.id))
+                .map(|item| item.name.clone())
+                .collect(),
+            unlocked_items: self
+                .items_index
+                .iter()
+                .filter(|item| self.unlocked_items.contains(&item.id))
+                .map(|item| item.name.clone())
+                .collect(),
+        }
+    }
+
+    /// Apply a transaction planned against the current state.
+    fn apply(&mut self, transaction: &Transaction) {
+        self.code_lines += transaction.lines_delta;
+        if transaction.owned_after < COUNT_EPSILON {
+            self.owned_items.remove(&transaction.item_id);
+        } else {
+            self.owned_items
+                .insert(transaction.item_id, transaction.owned_after);
+        }
+    }
+
+    /// The `unlock_cost` still to pay before buying `item`, none once it can be bought.
+    fn unlock_cost(&self, item: &Item) -> Option<u64> {
+        item.unlock_cost
+            .filter(|_| !self.unlocked_items.contains(&item.id))
+    }
+
+    /// The items of the item list, only the favorites with `favorites_only`.
+    fn listed_items(&self) -> Vec<&Item> {
+        self.items_index
+            .iter()
+            .filter(|item| !self.favorites_only || self.favorites.contains(&item.id))
+            .collect()
+    }
+
+    /// Find an item by its name, or else by one of the aliases from the config.
+    ///
+    /// A real item name always wins over an alias with the same spelling.
+    fn find_item(&self, name: &str) -> Option<&Item> {
+        let by_name = |name: &str| self.items_index.iter().find(|i| i.name == name);
+        by_name(name).or_else(|| self.config.aliases.get(name).and_then(|n| by_name(n)))
+    }
+
+    /// The item `item_id`, which must come from `items_index`.
+    fn item(&self, item_id: ItemId) -> &Item {
+        &self.items_index[item_id.0]
+    }
+
+    /// How many of the item `item_id` you own.
+    fn owned_count(&self, item_id: ItemId) -> f64 {
+        self.owned_items.get(&item_id).copied().unwrap_or(0.)
+    }
+
+    /// Whether you own as many of `item` as allowed.
+    fn is_maxed(&self, item: &Item) -> bool {
+        item.max
+            .is_some_and(|max| self.owned_count(item.id) >= max as f64)
+    }
+
+    /// Whether you have enough code lines to spend `cost`.
+    fn can_afford(&self, cost: u64) -> bool {
+        self.code_lines.can_pay(cost, self.config.affordability)
+    }
+
+    /// Code lines written each time you press `c`, 1 times the bought tiers multipliers.
+    fn click_power(&self) -> f64 {
+        self.config.click_tiers[..self.click_tier]
+            .iter()
+            .map(|tier| tier.multiplier)
+            .product()
+    }
+
+    /// What one `item` costs right now, see `price_at`.
+    fn price_of(&self, item: &Item) -> u64 {
+        price_at(item.cost, self.total_cps(), self.config.dynamic_cost)
+    }
+
+    /// Code lines produced each second by all owned items.
+    fn total_cps(&self) -> f64 {
+        self.cps_with(&self.owned_items)
+    }
+
+    /// Code lines that would be produced each second owning `owned` instead.
+    ///
+    /// Summed in catalog order, so a replayed game rounds exactly like the recorded one.
+    fn cps_with(&self, owned: &HashMap<ItemId, f64>) -> f64 {
+        self.items_index
+            .iter()
+            .filter_map(|item| Some(owned.get(&item.id)? * self.unit_cps_with(item.id, owned)))
+            .sum()
+    }
+
+    /// Code lines produced each second by one `item_id`, synergies included.
+    fn unit_cps(&self, item_id: ItemId) -> f64 {
+        self.unit_cps_with(item_id, &self.owned_items)
+    }
+
+    /// Code lines one `item_id` would produce each second owning `owned`,
+    /// prestige upgrades included.
+    fn unit_cps_with(&self, item_id: ItemId, owned: &HashMap<ItemId, f64>) -> f64 {
+        let bonus: f64 = self
+            .synergies
+            .iter()
+            .filter(|synergy| synergy.target_id == item_id)
+            .map(|synergy| {
+                let sources = owned.get(&synergy.source_id).copied().unwrap_or(0.);
+                synergy.per_unit_bonus * sources
+            })
+            .sum();
+        self.item(item_id).cps * (1. + bonus) * self.prestige_multiplier()
+    }
+
+    /// Owned items with the code lines per second they produce, the most productive first.
+    ///
+    /// Items producing as much are ordered by id, so the ranking doesn't flicker.
+    fn production_ranking(&self) -> Vec<(ItemId, f64)> {
+        let mut ranking: Vec<(ItemId, f64)> = self
+            .owned_items
+            .iter()
+            .map(|(item_id, item_count)| (*item_id, item_count * self.unit_cps(*item_id)))
+            .collect();
+        ranking.sort_by(|(id_a, cps_a), (id_b, cps_b)| cps_b.total_cmp(cps_a).then(id_a.cmp(id_b)));
+        ranking
+    }
+
+    /// Add freshly produced code lines.
+    ///
+    /// `lines` is negative when maintenance costs outweigh production,
+    /// code lines then drain down to zero but never below. A drain isn't
+    /// production, the produced counters only count what was gained.
+    fn produce(&mut self, lines: Lines) {
+        let before = self.code_lines;
+        self.code_lines = (self.code_lines + lines).max(Lines::ZERO);
+        let gained = (self.code_lines - before).max(Lines::ZERO);
+        self.stats.lines_produced += gained;
+        self.lifetime_lines += gained;
+        self.gains
+            .push(self.clock.now(), (self.code_lines - before).0);
+        self.notify_threshold();
+    }
+
+    /// Highest threshold from the config that `lines` is over, 0 if none.
+    fn threshold_below(&self, lines: Lines) -> u64 {
+        self.config
+            .thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| lines.0 >= *threshold as f64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Celebrate going over a new threshold.
+    ///
+    /// Each threshold is only celebrated once per game: spending code lines
+    /// and producing them again doesn't count.
+    fn notify_threshold(&mut self) {
+        let threshold = self.threshold_below(self.code_lines);
+        if threshold > self.reached_threshold {
+            self.reached_threshold = threshold;
+            self.stats.thresholds_reached += 1;
+            self.log
+                .push(tf(Msg::ThresholdReached, self.lang, &[&threshold]));
+            self.highlight_until = Some(self.clock.now() + HIGHLIGHT_DURATION);
+            self.ring = self.config.bell;
+        }
+    }
+
+    /// Start flashing if the code lines changed sharply since last time, see `flash_for`.
+    fn track_flash(&mut self, now: Instant) {
+        if let Some(flash) = flash_for(self.last_balance, self.code_lines) {
+            self.flash = Some((flash, now + FLASH_DURATION));
+        }
+        self.last_balance = self.code_lines;
+    }
+
+    /// Verify the game state is consistent, describing the first broken rule otherwise.
+    ///
+    /// Meant to catch bugs of new features, with F11 in `--debug`.
+    fn check_invariants(&self) -> Result<(), String> {
+        if self.code_lines.0.is_nan() || self.code_lines.0 < 0. {
+            return Err(format!("code lines are {}", self.code_lines));
+        }
+        for (item_id, count) in &self.owned_items {
+            let Some(item) = self.items_index.get(item_id.0) else {
+                return Err(format!("owning {count} of unknown item {}", item_id.0));
+            };
+            if !count.is_finite() || *count <= 0. {
+                return Err(format!("owning {count} {}", item.name));
+            }
+            if !self.config.fractional_items && count.fract() != 0. {
+                return Err(format!(
+                    "owning {count} {} without fractional_items",
+                    item.name
+                ));
+            }
+            if item.max.is_some_and(|max| *count > max as f64) {
+                return Err(format!("owning {count} {}, more than allowed", item.name));
+            }
+        }
+        if self.click_tier > self.config.click_tiers.len() {
+            return Err(format!("click tier {} doesn't exist", self.click_tier));
+        }
+        let cps = self.total_cps();
+        let recomputed: f64 = self
+            .production_ranking()
+            .iter()
+            .map(|(_, production)| production)
+            .sum();
+        if !cps.is_finite() || (cps - recomputed).abs() > 1e-6 * cps.abs().max(1.) {
+            return Err(format!("production is {cps} but items sum to {recomputed}"));
+        }
+        Ok(())
+    }
+
+    /// Tell the time with `clock` from now on, the session starting over on it.
+    fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        let now = clock.now();
+        self.stats.started = now;
+        self.gains = GainWindow::new(GAIN_WINDOW, now);
+        self.last_activity = now;
+        self.clock = clock;
+    }
+
+    /// Log when the code lines start or stop growing.
+    ///
+    /// Losing lines is shown all the time with its countdown, see `ui`.
+    fn track_outlook(&mut self) {
+        let outlook = outlook(self.code_lines, self.total_cps());
+        let changed = std::mem::discriminant(&outlook) != std::mem::discriminant(&self.outlook);
+        self.outlook = outlook;
+        match outlook {
+            Outlook::Growing if changed => {
+                self.log.push(t(Msg::LinesGrowing, self.lang).to_string())
+            }
+            Outlook::Stable if changed => self.log.push(t(Msg::LinesStable, self.lang).to_string()),
+            _ => {}
+        }
+    }
+
+    /// `secs` seconds of production, credited in one go.
+    ///
+    /// Production only changes with purchases, so crediting them at once is the same
+    /// as one second at a time, without looping after the game was suspended.
+    ///
+    /// A production or code lines count that isn't a finite number would silently
+    /// break every comparison: nothing is produced then, the lines are brought back
+    /// to a number and the player is told once in the log.
+    fn update(&mut self, secs: u64) {
+        let cps = self.total_cps();
+        // le conseil ne dépend que des items possédés, pas des lignes produites ensuite
+        self.advice = double_cps_advice(self);
+        self.track_outlook();
+        self.played_secs += secs;
+        for item in &self.items_index {
+            let owned = self.owned_items.get(&item.id).copied().unwrap_or(0.);
+            self.owned_samples[item.id.0].push(owned);
+        }
+        if !cps.is_finite() {
+            self.report_not_finite(ClidleError::ProductionNotFinite(cps));
+            return;
+        }
+        self.stats.peak_cps = self.stats.peak_cps.max(cps);
+        self.peak_cps = self.peak_cps.max(cps);
+        let mut produced = self.noisy_cps() * secs as f64;
+        if self.auto_code {
+            produced += self.click_power() * secs as f64;
+        }
+        self.produce(Lines(produced));
+        let lines = self.code_lines.0;
+        if !lines.is_finite() {
+            self.code_lines = if lines.is_nan() {
+                Lines::ZERO
+            } else {
+                Lines(f64::MAX)
+            };
+            self.report_not_finite(ClidleError::LinesNotFinite(lines));
+            return;
+        }
+        self.reported_not_finite = false;
+    }
+
+    /// `total_cps` with each item jittered by its `cps_variance`, same on average.
+    fn noisy_cps(&mut self) -> f64 {
+        let mut cps = 0.;
+        // même ordre que `cps_with`, pour arrondir pareil sans variance
+        for index in 0..self.items_index.len() {
+            let item = &self.items_index[index];
+            let Some(owned) = self.owned_items.get(&item.id) else {
+                continue;
+            };
+            let variance = item.cps_variance.clamp(0., 1.);
+            let item_cps = owned * self.unit_cps(item.id);
+            cps += if variance > 0. {
+                item_cps * self.rng.jitter(variance)
+            } else {
+                item_cps
+            };
         }
+        cps
     }
 
-    fn update(&mut self) {
-        for (item_id, item_count) in self.owned_items.iter() {
-            let item_type = self.items_index.get(*item_id).unwrap();
-            self.code_lines += *item_count as f64 * item_type.cps;
+    /// Log `error` unless it was already reported since things were last fine.
+    fn report_not_finite(&mut self, error: ClidleError) {
+        if !self.reported_not_finite {
+            self.reported_not_finite = true;
+            self.log.push(tf(
+                Msg::ErrorLine,
+                self.lang,
+                &[&error.localized(self.lang)],
+            ));
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    // la config est lue avant de toucher au terminal, pour pouvoir afficher l'erreur
+    if let Some(path) = args.replay {
+        let recording = Recording::read(&path)?;
+        let end = replay(&recording, args.items.as_deref().unwrap_or(ITEMS_PATH))?;
+        println!("{}", serde_json::to_string_pretty(&end)?);
+        if recording.end.is_some_and(|recorded| recorded != end) {
+            eprintln!("{}", t(Msg::ReplayDiffers, Lang::English));
+        }
+        return Ok(());
+    }
+    let config = Config::load(CONFIG_PATH)?;
+    if args.print_config {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+    // lire les items sur l'entrée standard doit se faire avant que la boucle de jeu s'en serve
+    let items_path = args.items.as_deref().unwrap_or(ITEMS_PATH);
+    // le catalogue est gardé tel quel pour l'enregistrement de la partie
+    let created = read_catalog(items_path, io::stdin().lock())
+        .and_then(|json| Ok((App::with_catalog(config, &json, items_path)?, json)));
+    let (mut app, catalog) = match created {
+        Ok(created) => created,
+        Err(err) => {
+            // le message seul, lisible, plutôt que la structure de l'erreur
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    if args.list_items {
+        print!("{}", items_table(&app.items_index));
+        return Ok(());
+    }
+    if let Some(save) = Save::read(SAVE_PATH)? {
+        app.restore(save);
+    }
+    if args.debug {
+        app.debug = Some(DebugInfo::default());
+    }
+    if args.export && app.config.ironman {
+        eprintln!("{}", ClidleError::ForbiddenInIronman);
+        std::process::exit(1);
+    }
+    if args.export {
+        println!("{}", app.snapshot().export()?);
+        return Ok(());
+    }
+    if let Some(path) = args.owned_csv {
+        fs::write(path, owned_csv(&app))?;
+        return Ok(());
+    }
+    if args.record.is_some() {
+        app.recording = Some(Recording::new(app.config.clone(), catalog, app.snapshot()));
+    }
+
+    // sans pouvoir créer le verrou on ne pourra sans doute pas sauvegarder non plus, la fin le dira
+    let lock = SaveLock::acquire(SAVE_PATH);
+    let read_only = matches!(lock, Ok(None)) && app.config.read_only_when_locked;
+    // une partie ironman n'écrit que la sauvegarde qu'elle tient
+    if app.config.ironman && !read_only {
+        app.autosave = Some(SAVE_PATH.to_string());
+    }
+    if let Ok(None) = lock {
+        let message = if read_only {
+            Msg::SaveLockedReadOnly
+        } else {
+            Msg::SaveLocked
+        };
+        app.log.push(tf(message, app.lang, &[&SAVE_PATH]));
+    }
+
     // setup terminal
     // le ? permet de faire un early return en cas d'erreur.
     // https://doc.rust-lang.org/book/ch09-02-recoverable-errors-with-result.html#a-shortcut-for-propagating-errors-the--operator
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // crossterm, ou termion avec la feature `termion`, voir le module term
+    let (mut terminal, mut input) = term::setup()?;
 
-    // create app and run it
-    let app = App::new();
-    let res = run_app(&mut terminal, app);
+    // run app
+    let res = run_app(&mut terminal, input.as_mut(), &mut app);
 
     // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    term::restore(terminal)?;
 
     if let Err(err) = res {
         println!("{err:?}")
     }
 
+    if read_only {
+        println!("{}", tf(Msg::SaveLockedReadOnly, app.lang, &[&SAVE_PATH]));
+    } else if let Err(err) = app.save(SAVE_PATH) {
+        println!("{err}")
+    }
+    // relâché seulement une fois la sauvegarde écrite
+    drop(lock);
+
+    if let (Some(path), Some(mut recording)) = (args.record, app.recording.take()) {
+        recording.end = Some(app.snapshot());
+        recording.write(path)?;
+    }
+
+    if let Some(path) = args.stats_out {
+        app.stats.write(path, app.clock.now())?;
+    }
+
     Ok(())
 }
 
 #[derive(Debug)]
 enum ClidleError {
-    BuyingItemNotKnown(String),
+    /// (typed name, items with a close name)
+    BuyingItemNotKnown(String, Vec<String>),
+    /// (typed name, items with a close name)
+    SellingItemNotKnown(String, Vec<String>),
+    /// (item name, owned, wanted)
+    SellingItemNotOwned(String, f64, f64),
+    /// (item name, max owned)
+    ItemMaxedOut(String, u64),
+    /// (item name, price, missing code lines)
+    NotEnoughLines(String, u64, u64),
+    /// (item, unlock cost) the item must be unlocked before being bought
+    ItemLocked(String, u64),
+    /// (item, unlock cost, missing code lines)
+    UnlockTooExpensive(String, u64, u64),
+    /// (item name, price, reserve) a purchase would spend lines kept by `min_reserve`
+    ReserveBreached(String, u64, u64),
+    /// (item name, unit price, budget) not even one fits in `budget_fraction`
+    OverBudget(String, u64, u64),
+    NoPurchaseToUndo,
+    NoClickTierLeft,
+    /// (price, missing code lines)
+    ClickTierTooExpensive(u64, u64),
+    /// code lines became NaN, nothing can be bought
+    LinesNotANumber,
+    /// (production per second) the items produce NaN or infinite lines
+    ProductionNotFinite(f64),
+    /// (code lines) the code lines became NaN or infinite, they were brought back
+    LinesNotFinite(f64),
+    /// selling and undoing are disabled by `hardcore`
+    ForbiddenInHardcore,
+    /// exporting and importing saves are disabled by `ironman`
+    ForbiddenInIronman,
+    /// (key, action, other action) two actions share a key
+    KeyConflict(char, String, String),
+    /// (code lines needed) a prestige wouldn't earn any commit yet
+    NothingToPrestige(u64),
+    /// the prestige upgrade typed
+    UpgradeNotKnown(String),
+    UpgradeAlreadyBought(String),
+    /// buying the favorites without any
+    NoFavorite,
+    /// (upgrade name, cost, commits owned)
+    NotEnoughCommits(String, u64, u64),
+    /// (path, reason)
+    ItemsUnreadable(String, String),
+    /// (path, line, column, reason) the items aren't what `Item` expects
+    ItemsFileMalformed(String, usize, usize, String),
+    /// (path, reason)
+    RecordingUnreadable(String, String),
+    /// the command typed in command mode
+    UnknownCommand(String),
+    /// (step number from 1, its error) a macro stopped there
+    MacroStepFailed(usize, Box<ClidleError>),
+    /// (step number from 1) a macro stopped there to wait for the player to confirm
+    MacroStepUnconfirmed(usize),
+    /// a purchase or sale typed without its item
+    MissingItem,
+    /// the quantity typed to buy or sell
+    InvalidQuantity(String),
+    /// the reason why the typed save can't be imported
+    ImportInvalid(String),
+    /// (source id, target id) a synergy refers to an item that doesn't exist
+    SynergyItemMissing(usize, usize),
+    ConfigMalformed(String),
+    SaveMalformed(String),
+    SaveFailed(String),
+    /// the unknown command line argument
+    UnknownArgument(String),
+    /// the command line flag given without its value
+    ArgumentNeedsValue(String),
 }
 
 // les deux blocs impl suivant sont des implémentaition concrètes de trait de la lib standard.
@@ -143,9 +1204,89 @@ enum ClidleError {
 
 impl Error for ClidleError {}
 
+impl ClidleError {
+    /// The error message in `lang`.
+    fn localized(&self, lang: Lang) -> String {
+        match self {
+            ClidleError::BuyingItemNotKnown(item, candidates)
+            | ClidleError::SellingItemNotKnown(item, candidates) => {
+                let mut message = tf(Msg::UnknownItem, lang, &[item]);
+                if !candidates.is_empty() {
+                    message += &tf(Msg::DidYouMean, lang, &[&candidates.join(", ")]);
+                }
+                message
+            }
+            ClidleError::SellingItemNotOwned(item, owned, wanted) => tf(
+                Msg::NotOwned,
+                lang,
+                &[&format_count(*owned), item, &format_count(*wanted)],
+            ),
+            ClidleError::ItemMaxedOut(item, max) => tf(Msg::ItemMaxedOut, lang, &[max, item]),
+            ClidleError::NotEnoughLines(item, price, missing) => {
+                tf(Msg::NotEnoughLines, lang, &[price, item, missing])
+            }
+            ClidleError::ItemLocked(item, cost) => tf(Msg::ItemLocked, lang, &[item, cost]),
+            ClidleError::UnlockTooExpensive(item, cost, missing) => {
+                tf(Msg::UnlockTooExpensive, lang, &[cost, item, missing])
+            }
+            ClidleError::ReserveBreached(item, price, reserve) => {
+                tf(Msg::ReserveBreached, lang, &[item, price, reserve])
+            }
+            ClidleError::OverBudget(item, price, budget) => {
+                tf(Msg::OverBudget, lang, &[item, price, budget])
+            }
+            ClidleError::NoPurchaseToUndo => t(Msg::NoPurchaseToUndo, lang).to_string(),
+            ClidleError::NoClickTierLeft => t(Msg::NoClickTierLeft, lang).to_string(),
+            ClidleError::LinesNotANumber => t(Msg::LinesNotANumber, lang).to_string(),
+            ClidleError::ImportInvalid(reason) => tf(Msg::ImportInvalid, lang, &[reason]),
+            ClidleError::UnknownCommand(command) => tf(Msg::UnknownCommand, lang, &[command]),
+            ClidleError::MacroStepFailed(step, error) => {
+                tf(Msg::MacroStepFailed, lang, &[step, &error.localized(lang)])
+            }
+            ClidleError::MacroStepUnconfirmed(step) => tf(Msg::MacroStepUnconfirmed, lang, &[step]),
+            ClidleError::MissingItem => t(Msg::MissingItem, lang).to_string(),
+            ClidleError::InvalidQuantity(quantity) => tf(Msg::InvalidQuantity, lang, &[quantity]),
+            ClidleError::ProductionNotFinite(cps) => tf(Msg::ProductionNotFinite, lang, &[cps]),
+            ClidleError::LinesNotFinite(lines) => tf(Msg::LinesNotFinite, lang, &[lines]),
+            ClidleError::ForbiddenInHardcore => t(Msg::ForbiddenInHardcore, lang).to_string(),
+            ClidleError::ForbiddenInIronman => t(Msg::ForbiddenInIronman, lang).to_string(),
+            ClidleError::KeyConflict(key, action, other) => {
+                tf(Msg::KeyConflict, lang, &[key, action, other])
+            }
+            ClidleError::NothingToPrestige(lines) => tf(Msg::NothingToPrestige, lang, &[lines]),
+            ClidleError::UpgradeNotKnown(name) => tf(Msg::UpgradeNotKnown, lang, &[name]),
+            ClidleError::UpgradeAlreadyBought(name) => tf(Msg::UpgradeAlreadyBought, lang, &[name]),
+            ClidleError::NoFavorite => t(Msg::NoFavorite, lang).to_string(),
+            ClidleError::NotEnoughCommits(name, cost, commits) => {
+                tf(Msg::NotEnoughCommits, lang, &[name, cost, commits])
+            }
+            ClidleError::ClickTierTooExpensive(price, missing) => {
+                tf(Msg::ClickTierTooExpensive, lang, &[price, missing])
+            }
+            ClidleError::ItemsUnreadable(path, reason)
+            | ClidleError::RecordingUnreadable(path, reason) => {
+                tf(Msg::CannotRead, lang, &[path, reason])
+            }
+            ClidleError::ItemsFileMalformed(path, line, column, reason) => {
+                tf(Msg::FileMalformed, lang, &[path, line, column, reason])
+            }
+            ClidleError::SynergyItemMissing(source, target) => {
+                tf(Msg::SynergyItemMissing, lang, &[source, target])
+            }
+            ClidleError::ConfigMalformed(reason) => {
+                tf(Msg::CannotRead, lang, &[&CONFIG_PATH, reason])
+            }
+            ClidleError::SaveMalformed(reason) => tf(Msg::CannotRead, lang, &[&SAVE_PATH, reason]),
+            ClidleError::SaveFailed(reason) => tf(Msg::SaveFailed, lang, &[&SAVE_PATH, reason]),
+            ClidleError::UnknownArgument(arg) => tf(Msg::UnknownArgument, lang, &[arg]),
+            ClidleError::ArgumentNeedsValue(flag) => tf(Msg::ArgumentNeedsValue, lang, &[flag]),
+        }
+    }
+}
+
 impl fmt::Display for ClidleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{self:?}")
+        write!(f, "{}", self.localized(Lang::English))
     }
 }
 
@@ -153,237 +1294,5985 @@ impl fmt::Display for ClidleError {
 enum GameState {
     /// Item you wanna buy need to be parsed.
     BuyItem(String),
+    /// Item you wanna sell need to be parsed too.
+    SellItem(String),
+    /// A purchase or sale typed in command mode, already parsed.
+    Trade(Command),
+    /// Cancel the last purchase.
+    Undo,
+    /// Start a new game.
+    Reset,
+    /// Start over for commits.
+    Prestige,
+    /// Spend commits on a prestige upgrade.
+    BuyUpgrade(String),
+    /// Mark or unmark an item as favorite, need to be parsed.
+    ToggleFavorite(String),
+    /// Buy one of each favorite item.
+    BuyFavorites,
+    /// Add an item to the comparison, need to be parsed.
+    Compare(String),
+    /// Replace the game by an exported save.
+    Import(String),
+    /// Pay the unlock cost of an item, the player confirmed it.
+    Unlock(String),
+    /// Buy an expensive item, the player confirmed it.
+    ConfirmBuy { item: String, count: f64 },
+    /// Sell the last copies of an item, the player confirmed it.
+    ConfirmSell { item: String, count: f64 },
+    /// Buy the next click tier.
+    UpgradeClick,
+    /// Do several typed commands in a row, see `parse_macro`.
+    Macro(Vec<Action>),
+    /// Sell one of the least productive owned item.
+    SellWorst,
     /// Nothing from input, at least it's fast to manage
     Noop,
     /// Stop gaming, go code for work government said.
     Quit,
+    /// Stop right away on Ctrl-C, without the session summary.
+    Interrupt,
 }
 
-/// Check if you can buy an item and buy it.
+/// A change to both the code lines and the owned items.
+///
+/// It is fully checked when planned by `plan_buy` or `plan_sell`, so applying it
+/// can't fail half way, leaving lines spent without the items.
+struct Transaction {
+    item_id: ItemId,
+    /// canonical name of the item
+    name: String,
+    /// how many items change hands
+    count: f64,
+    /// how many items you own afterwards
+    owned_after: f64,
+    /// code lines won (or lost if negative)
+    lines_delta: Lines,
+}
+
+/// Check that you can buy `count` of `item`, without buying anything yet.
 ///
 /// ## Errors
 ///
-/// May return the infamous `ClidleError::BuyingItemNotKnown` if
-/// your item is not known.
-fn buy_item(app: &mut App, item: String) -> Result<(), ClidleError> {
-    let (item_id, item_type) = app
-        .items_index
-        .iter()
-        .enumerate()
-        .find(|(_, i)| i.name == item)
-        .ok_or_else(|| ClidleError::BuyingItemNotKnown(item.clone()))?;
-
-    let count = 1; // TODO buy multiple
-    if item_type.cost * count < app.code_lines.floor() as u64 {
-        app.code_lines -= (item_type.cost * count) as f64;
-        app.owned_items
-            .entry(item_id)
-            .and_modify(|e| *e += count)
-            .or_insert(count);
+/// `ClidleError::BuyingItemNotKnown` if your item is not known,
+/// `ClidleError::ItemLocked` if its `unlock_cost` wasn't paid yet,
+/// `ClidleError::ItemMaxedOut` if you would own more than allowed,
+/// `ClidleError::LinesNotANumber` if the code lines got corrupted,
+/// `ClidleError::NotEnoughLines` if it's too expensive, or
+/// `ClidleError::ReserveBreached` if it would leave less than `min_reserve` lines.
+fn plan_buy(app: &App, item: String, count: f64) -> Result<Transaction, ClidleError> {
+    let Some(item_type) = app.find_item(&item) else {
+        let candidates = close_names(app, &item);
+        return Err(ClidleError::BuyingItemNotKnown(item, candidates));
+    };
+    if let Some(cost) = app.unlock_cost(item_type) {
+        return Err(ClidleError::ItemLocked(item_type.name.clone(), cost));
     }
-    Ok(())
-}
-
-/// Handles inputs if it's successful you get a GameState if not you may end up with
-/// an IO error.
-fn handle_input(app: &mut App) -> io::Result<GameState> {
-    if let Event::Key(key) = event::read()? {
-        match app.input_mode {
-            InputMode::Normal => match key.code {
-                KeyCode::Char('b') => {
-                    app.input_mode = InputMode::Buy;
-                }
-                KeyCode::Char('c') => {
-                    app.code_lines += 1.;
-                }
-                KeyCode::Char('s') => {
-                    app.input_mode = InputMode::Sell;
-                }
-                KeyCode::Char('q') => {
-                    return Ok(GameState::Quit);
-                }
-                _ => {}
-            },
-            InputMode::Buy => match key.code {
-                KeyCode::Char(c) => {
-                    app.input.push(c);
-                }
-                KeyCode::Backspace => {
-                    app.input.pop();
-                }
-                KeyCode::Enter => {
-                    return Ok(GameState::BuyItem(app.input.drain(..).collect()));
-                }
-                KeyCode::Esc => {
-                    app.input_mode = InputMode::Normal;
-                }
-                _ => {}
-            },
-            InputMode::Sell => todo!(),
+    let owned_after = app.owned_count(item_type.id) + count;
+    if let Some(max) = item_type.max {
+        if owned_after > max as f64 + COUNT_EPSILON {
+            return Err(ClidleError::ItemMaxedOut(item_type.name.clone(), max));
         }
     }
-    Ok(GameState::Noop)
+    let price = bulk_price(app, app.price_of(item_type), count);
+    if app.code_lines.0.is_nan() {
+        return Err(ClidleError::LinesNotANumber);
+    }
+    if !app.can_afford(price) {
+        let missing = app.code_lines.missing_for(price, app.config.affordability);
+        return Err(ClidleError::NotEnoughLines(
+            item_type.name.clone(),
+            price,
+            missing,
+        ));
+    }
+    let reserve = app.config.min_reserve;
+    if app.code_lines.0 - (price as f64) < reserve as f64 {
+        return Err(ClidleError::ReserveBreached(
+            item_type.name.clone(),
+            price,
+            reserve,
+        ));
+    }
+    Ok(Transaction {
+        item_id: item_type.id,
+        name: item_type.name.clone(),
+        count,
+        owned_after,
+        lines_delta: -Lines::from_u64(price),
+    })
 }
 
-/// contient la boucle de jeu
-///
-/// Ici Terminal est un type générique qui a besoin d'une interface (un trait)
-/// pour fonctionner. Ici en particulier le trait backend permet d'interagir avec le terminal
-/// (dessin, gestion du curseur, etc)
+/// Check that you can sell `count` of `item`, getting back half of its cost.
 ///
+/// ## Errors
 ///
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<(), Box<dyn Error>> {
-    // pour vérifier si il faut mettre à jour l'état du jeu
-    let mut last_tick = Instant::now();
+/// `ClidleError::ForbiddenInHardcore` in hardcore mode,
+/// `ClidleError::SellingItemNotKnown` if your item is not known, or
+/// `ClidleError::SellingItemNotOwned` if you don't have enough.
+fn plan_sell(app: &App, item: String, count: f64) -> Result<Transaction, ClidleError> {
+    if app.config.hardcore {
+        return Err(ClidleError::ForbiddenInHardcore);
+    }
+    let Some(item_type) = app.find_item(&item) else {
+        let candidates = close_names(app, &item);
+        return Err(ClidleError::SellingItemNotKnown(item, candidates));
+    };
+    let owned = app.owned_count(item_type.id);
+    let owned_after = remove_count(owned, count)
+        .ok_or_else(|| ClidleError::SellingItemNotOwned(item_type.name.clone(), owned, count))?;
+    Ok(Transaction {
+        item_id: item_type.id,
+        name: item_type.name.clone(),
+        count,
+        owned_after,
+        lines_delta: Lines(price_for(item_type.cost, count) as f64 / 2.),
+    })
+}
 
-    loop {
-        // mise à jour de l'état du jeu
-        if last_tick.elapsed() >= Duration::from_secs(1) {
-            app.update();
-            last_tick = Instant::now();
-        }
+/// How many close names an unknown item error suggests at most.
+const MAX_CANDIDATES: usize = 3;
+
+/// Names of the items that may be what the player meant by `typed`: those
+/// `filter_items` finds, or else those `typed` starts with, like a typo at the end.
+fn close_names(app: &App, typed: &str) -> Vec<String> {
+    let typed = typed.trim();
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let mut close = filter_items(&app.items_index, typed);
+    if close.is_empty() {
+        close = app
+            .items_index
+            .iter()
+            .filter(|item| typed.starts_with(&item.name))
+            .collect();
+    }
+    close
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|item| item.name.clone())
+        .collect()
+}
 
-        // ici l'argument de la fonction est une closure, une autre fonction anonyme
-        terminal.draw(|f| ui(f, &mut app))?;
+/// Owned counts closer to zero than this are nothing, so selling back what was
+/// bought by fractions leaves none despite float rounding.
+const COUNT_EPSILON: f64 = 1e-9;
 
-        // la fonction poll permet de vérifier si un evenement s'est rendu disponible
-        // avant la fin du temps inparti
-        if poll(Duration::from_millis(100))? {
-            let state = handle_input(&mut app)?;
-            match state {
-                GameState::BuyItem(item_string) => {
-                    // On veut pouvoir afficher l'erreur et sans paniquer
-                    // en effet, on ne sait si ce que le joueur a entré est valide ou non
-                    app.error = buy_item(&mut app, item_string)
-                }
-                GameState::Noop => {}
-                GameState::Quit => return Ok(()),
-            }
-        }
+/// What is left of `owned` items once `count` are gone, none if there isn't enough.
+fn remove_count(owned: f64, count: f64) -> Option<f64> {
+    let left = owned - count;
+    if left < -COUNT_EPSILON {
+        None
+    } else {
+        Some(left.max(0.))
     }
 }
 
-// Permet de gérer tout l'affichage
-fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(2)
-        .constraints(
-            [
-                Constraint::Length(1),
-                Constraint::Length(3),
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
-            ]
-            .as_ref(),
-        )
-        .split(f.size());
+/// The price of `count` items at `unit_price` each, rounded up for a fraction of item.
+///
+/// Whole counts are multiplied as integers, so big integer prices stay exact.
+fn price_for(unit_price: u64, count: f64) -> u64 {
+    if count.fract() == 0. {
+        // `as` sature, comme la multiplication
+        unit_price.saturating_mul(count as u64)
+    } else {
+        (unit_price as f64 * count).ceil() as u64
+    }
+}
 
-    let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
-            vec![
-                Span::raw(format!("Owning {:.2} code lines, ", app.code_lines)),
-                Span::raw("Press "),
-                Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to exit, "),
-                Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to code, "),
-                Span::styled("b", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to start buying, "),
-                Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to start selling."),
-            ],
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
-        ),
-        InputMode::Buy => (
-            vec![
-                Span::raw(format!("Owning {:.2} code lines, ", app.code_lines)),
-                Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop buying, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" buy"),
-            ],
-            Style::default(),
-        ),
-        InputMode::Sell => (
-            vec![
-                Span::raw(format!("Owning {:.2} code lines, ", app.code_lines)),
-                Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop selling, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to sell"),
-            ],
-            Style::default(),
-        ),
+/// Selling gives back half of the price, so a discount of half or more would make
+/// buying and selling again a way to make lines.
+const MAX_BULK_DISCOUNT: f64 = 49.;
+
+/// The percent taken off buying `count` items at once, from the biggest
+/// `bulk_discounts` tier reached, none below the first tier.
+fn bulk_discount(app: &App, count: f64) -> Option<f64> {
+    app.config
+        .bulk_discounts
+        .iter()
+        .filter(|tier| count >= tier.min_count as f64)
+        .max_by_key(|tier| tier.min_count)
+        .map(|tier| tier.percent.clamp(0., MAX_BULK_DISCOUNT))
+}
+
+/// `price_for` with the bulk discount of `count`, rounded up to whole lines.
+fn bulk_price(app: &App, unit_price: u64, count: f64) -> u64 {
+    let price = price_for(unit_price, count);
+    match bulk_discount(app, count) {
+        Some(percent) => (price as f64 * (1. - percent / 100.)).ceil() as u64,
+        None => price,
+    }
+}
+
+/// An item count for the player, with at most two decimals and none when whole.
+fn format_count(count: f64) -> String {
+    ((count * 100.).round() / 100.).to_string()
+}
+
+/// Whether items change hands for code lines or the other way around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verb {
+    Buy,
+    Sell,
+}
+
+/// How many items to buy or sell at once.
+#[derive(Debug, PartialEq)]
+enum Quantity {
+    Count(f64),
+    /// just enough to reach the next milestone, only to buy
+    NextMilestone,
+    /// as many as affordable to buy, everything owned to sell
+    Max,
+}
+
+/// A purchase or a sale as typed, before looking up the item.
+#[derive(Debug, PartialEq)]
+struct Command {
+    verb: Verb,
+    item: String,
+    quantity: Quantity,
+}
+
+/// Read a whole purchase or sale: `buy dev 10`, `sell git all`.
+///
+/// ## Errors
+///
+/// `ClidleError::UnknownCommand` if it doesn't start with `buy` or `sell`,
+/// see `parse_trade` for the others.
+fn parse_command(input: &str) -> Result<Command, ClidleError> {
+    let input = input.trim();
+    let (verb, rest) = input.split_once(' ').unwrap_or((input, ""));
+    let verb = match verb.to_lowercase().as_str() {
+        "buy" => Verb::Buy,
+        "sell" => Verb::Sell,
+        _ => return Err(ClidleError::UnknownCommand(input.to_string())),
     };
-    let mut text = Text::from(Spans::from(msg));
-    text.patch_style(style);
-    let help_message = Paragraph::new(text);
-    f.render_widget(help_message, chunks[0]);
+    parse_trade(verb, rest)
+}
 
-    let input = Paragraph::new(app.input.as_ref())
-        .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
-            InputMode::Buy => Style::default().fg(Color::Green),
-            InputMode::Sell => Style::default().fg(Color::Red),
-        })
-        .block(Block::default().borders(Borders::ALL).title("Input"));
-    f.render_widget(input, chunks[1]);
-    match app.input_mode {
-        InputMode::Normal =>
-            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
-            {}
+/// Read what follows the verb of a command, which is all the buy and sell modes ask for.
+///
+/// The quantity is an optional last word: a count (`dev 10`), `next` to reach the next
+/// milestone (`dev next`), or `all` or `max` for as many as possible. It defaults to one.
+/// A last word that looks like a number but isn't a positive count is an error rather
+/// than the end of the item name.
+///
+/// ## Errors
+///
+/// `ClidleError::MissingItem` if no item is given, or
+/// `ClidleError::InvalidQuantity` if the count isn't a positive number.
+fn parse_trade(verb: Verb, input: &str) -> Result<Command, ClidleError> {
+    let input = input.trim();
+    let (item, quantity) = match input.rsplit_once(' ') {
+        Some((item, word)) => match parse_quantity(word)? {
+            Some(quantity) => (item.trim_end(), quantity),
+            None => (input, Quantity::Count(1.)),
+        },
+        None => (input, Quantity::Count(1.)),
+    };
+    if item.is_empty() {
+        return Err(ClidleError::MissingItem);
+    }
+    Ok(Command {
+        verb,
+        item: item.to_string(),
+        quantity,
+    })
+}
 
-        InputMode::Sell | InputMode::Buy => {
-            // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-            f.set_cursor(
-                // Put cursor past the end of the input text
-                chunks[1].x + app.input.width() as u16 + 1,
-                // Move one line down, from the border to the input line
-                chunks[1].y + 1,
-            )
+/// The quantity `word` stands for, none if it isn't one and belongs to the item name.
+fn parse_quantity(word: &str) -> Result<Option<Quantity>, ClidleError> {
+    let quantity = match word.to_lowercase().as_str() {
+        "next" => Quantity::NextMilestone,
+        "all" | "max" => Quantity::Max,
+        _ => match word.parse::<f64>() {
+            // une quantité plus petite serait payée puis oubliée, voir `App::apply`
+            Ok(count) if count.is_finite() && count > 0. => {
+                Quantity::Count(count.max(COUNT_EPSILON))
+            }
+            Ok(_) => return Err(ClidleError::InvalidQuantity(word.to_string())),
+            // les chiffres ne font pas partie des noms d'items
+            Err(_) if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                return Err(ClidleError::InvalidQuantity(word.to_string()))
+            }
+            Err(_) => return Ok(None),
+        },
+    };
+    Ok(Some(quantity))
+}
+
+/// How many items `command` moves, now that the game state is known.
+///
+/// ## Errors
+///
+/// `ClidleError::InvalidQuantity` for a fraction without `fractional_items`
+/// or to sell up to a milestone, or `ClidleError::SellingItemNotOwned` to sell
+/// all of an item you don't own.
+fn command_count(app: &App, command: &Command) -> Result<f64, ClidleError> {
+    let item = app.find_item(&command.item);
+    let owned = item.map_or(0., |item| app.owned_count(item.id));
+    let count = match (&command.quantity, command.verb) {
+        (Quantity::Count(count), _) if !app.config.fractional_items && count.fract() != 0. => {
+            return Err(ClidleError::InvalidQuantity(format_count(*count)))
+        }
+        (Quantity::Count(count), _) => *count,
+        (Quantity::NextMilestone, Verb::Buy) => to_next_milestone(owned, app.config.milestone),
+        (Quantity::NextMilestone, Verb::Sell) => {
+            return Err(ClidleError::InvalidQuantity("next".to_string()))
+        }
+        (Quantity::Max, Verb::Buy) => item.map_or(1., |item| max_affordable(app, item)),
+        // tout vendre demande d'en avoir au moins un, sinon rien ne changerait de mains
+        (Quantity::Max, Verb::Sell) => match item {
+            Some(item) if owned < COUNT_EPSILON => {
+                return Err(ClidleError::SellingItemNotOwned(
+                    item.name.clone(),
+                    owned,
+                    1.,
+                ))
+            }
+            _ => owned,
+        },
+    };
+    Ok(count)
+}
+
+/// How many whole `item` you can afford and are allowed to own more, at least one
+/// so that buying them tells why it's impossible.
+///
+/// A free item without `max` is bought one at a time.
+fn max_affordable(app: &App, item: &Item) -> f64 {
+    let allowed = item
+        .max
+        .map(|max| (max as f64 - app.owned_count(item.id)).floor().max(0.));
+    let price = app.price_of(item);
+    // il faut strictement plus de lignes que le prix, voir `Lines::can_pay`,
+    // et en garder au moins `min_reserve` après l'achat, fractions comprises
+    let lines = app.code_lines;
+    let kept = (lines.0 - app.config.min_reserve as f64).floor() as u64;
+    let budget = kept.min(lines.spendable(app.config.affordability).saturating_sub(1));
+    let affordable = match budget.checked_div(price) {
+        Some(count) => count as f64,
+        None => allowed.unwrap_or(1.),
+    };
+    affordable.min(allowed.unwrap_or(f64::MAX)).max(1.)
+}
+
+/// The spinner quantity moved by `step`, between one and as many `item` as affordable.
+fn spin(app: &App, item: &str, step: i64) -> u64 {
+    let max = app
+        .find_item(item)
+        .map_or(1, |item| max_affordable(app, item) as u64);
+    app.buy_quantity
+        .saturating_add_signed(step)
+        .clamp(1, max.max(1))
+}
+
+/// How many items are needed to go from `owned` to the next multiple of `milestone`.
+///
+/// When `owned` is already a multiple, that's a whole `milestone` more.
+fn to_next_milestone(owned: f64, milestone: u64) -> f64 {
+    let milestone = milestone.max(1) as f64;
+    milestone - owned % milestone
+}
+
+/// Check if you can buy an item and buy it.
+///
+/// `item` may be the item name or an alias from the config, optionally
+/// followed by a quantity (see `parse_trade`). When buying several items
+/// either all of them are bought or none.
+///
+/// ## Errors
+///
+/// May return the infamous `ClidleError::BuyingItemNotKnown` if
+/// your item is not known, see `parse_trade` and `plan_buy` for the others.
+fn buy_item(app: &mut App, item: String) -> Result<(), ClidleError> {
+    trade(app, parse_trade(Verb::Buy, &item)?)
+}
+
+/// Buy or sell what `command` says, see `buy_item` and `sell_item`.
+///
+/// ## Errors
+///
+/// See `command_count`, `plan_buy` and `plan_sell`.
+fn trade(app: &mut App, command: Command) -> Result<(), ClidleError> {
+    let count = command_count(app, &command)?;
+    let item = command.item.as_str();
+    if command.verb == Verb::Sell {
+        return sell_checked(app, item, count);
+    }
+    // débloquer est un achat à part, l'item s'achète ensuite normalement
+    if let Some(found) = app.find_item(item) {
+        if let Some(cost) = app.unlock_cost(found) {
+            let item = found.name.clone();
+            let back_to = Box::new(std::mem::replace(&mut app.input_mode, InputMode::Normal));
+            app.input_mode = InputMode::ConfirmUnlock {
+                item,
+                cost,
+                back_to,
+            };
+            return Ok(());
+        }
+    }
+    let count = within_budget(app, item, count)?;
+    if let Some(limit) = app.config.confirm_above {
+        // un achat impossible s'arrête sur son erreur, pas besoin de confirmer
+        if let Ok(transaction) = plan_buy(app, item.to_string(), count) {
+            let price = (-transaction.lines_delta).as_u64();
+            if price > limit {
+                let back_to = Box::new(std::mem::replace(&mut app.input_mode, InputMode::Normal));
+                app.input_mode = InputMode::ConfirmBuy {
+                    item: item.to_string(),
+                    count,
+                    price,
+                    back_to,
+                };
+                return Ok(());
+            }
         }
     }
+    buy(app, item, count)
+}
 
-    let owned: Vec<ListItem> = app
-        .owned_items
-        .iter()
-        .map(|(item_id, item_count)| {
-            let item_type = app.items_index.get(*item_id).unwrap();
+/// `count` of `item`, or as many whole ones as `budget_fraction` of the code lines pays for.
+///
+/// Free items and unknown items go through untouched, `plan_buy` tells about the latter.
+///
+/// ## Errors
+///
+/// `ClidleError::OverBudget` if not even one fits in the budget.
+fn within_budget(app: &mut App, item: &str, count: f64) -> Result<f64, ClidleError> {
+    let Some(fraction) = app.config.budget_fraction else {
+        return Ok(count);
+    };
+    let Some(item_type) = app.find_item(item) else {
+        return Ok(count);
+    };
+    let price = app.price_of(item_type);
+    let budget = budget(app, fraction);
+    if price == 0 || bulk_price(app, price, count) <= budget {
+        return Ok(count);
+    }
+    let name = item_type.name.clone();
+    let affordable = budget / price;
+    if affordable == 0 {
+        return Err(ClidleError::OverBudget(name, price, budget));
+    }
+    app.log.push(tf(
+        Msg::BudgetTruncated,
+        app.lang,
+        &[&affordable, &format_count(count), &name, &budget],
+    ));
+    Ok(affordable as f64)
+}
 
-            let content = vec![Spans::from(Span::raw(format!(
-                "Owning {item_count} {} producing a total of {:.2} code line per second",
-                item_type.long_name,
-                *item_count as f64 * item_type.cps
-            )))];
-            ListItem::new(content)
-        })
-        .collect();
-    let owned = List::new(owned).block(Block::default().borders(Borders::ALL).title("Owned"));
-    f.render_widget(owned, chunks[2]);
+/// The code lines `fraction` of them allows to spend at once, see `budget_fraction`.
+fn budget(app: &App, fraction: f64) -> u64 {
+    // `as` sature, et des lignes négatives ne permettent rien
+    (app.code_lines.0.max(0.) * fraction.clamp(0., 1.)).floor() as u64
+}
+
+/// The log entry of a purchase, giving the unit price as well for bulk buys.
+fn bought_message(lang: Lang, transaction: &Transaction, price: u64) -> String {
+    let name = &transaction.name;
+    let owned = &format_count(transaction.owned_after);
+    let count = transaction.count;
+    if count == 1. {
+        tf(Msg::BoughtOne, lang, &[name, &price, owned])
+    } else {
+        let unit_price = if count > 0. {
+            (price as f64 / count) as u64
+        } else {
+            0
+        };
+        tf(
+            Msg::BoughtMany,
+            lang,
+            &[&format_count(count), name, &price, &unit_price, owned],
+        )
+    }
+}
+
+/// Buy `count` of `item` right away, confirmed or not.
+///
+/// ## Errors
+///
+/// See `plan_buy`.
+fn buy(app: &mut App, item: &str, count: f64) -> Result<(), ClidleError> {
+    let transaction = plan_buy(app, item.to_string(), count)?;
+    app.apply(&transaction);
+    // une fraction d'item compte comme un achat
+    app.stats.purchases += count.ceil() as u64;
+    app.lifetime_purchases += count.ceil() as u64;
+    let price = (-transaction.lines_delta).as_u64();
+    app.history.push(Purchase {
+        item_id: transaction.item_id,
+        count,
+        price,
+        at: app.clock.now(),
+    });
+    app.log.push(bought_message(app.lang, &transaction, price));
+    Ok(())
+}
+
+/// Pay the `unlock_cost` of `item` once, so that it can be bought from now on.
+///
+/// ## Errors
+///
+/// `ClidleError::BuyingItemNotKnown` if there is no such item,
+/// `ClidleError::UnlockTooExpensive` if the code lines don't cover the unlock cost,
+/// `ClidleError::ReserveBreached` if it would leave less than `min_reserve` lines, or
+/// `ClidleError::OverBudget` if it costs more than `budget_fraction` of the lines,
+/// like a purchase.
+fn unlock(app: &mut App, item: &str) -> Result<(), ClidleError> {
+    let Some(item_type) = app.find_item(item) else {
+        return Err(ClidleError::BuyingItemNotKnown(
+            item.to_string(),
+            close_names(app, item),
+        ));
+    };
+    let (id, name) = (item_type.id, item_type.name.clone());
+    // déjà débloqué, rien à payer
+    let Some(cost) = app.unlock_cost(item_type) else {
+        return Ok(());
+    };
+    if !app.can_afford(cost) {
+        let missing = app.code_lines.missing_for(cost, app.config.affordability);
+        return Err(ClidleError::UnlockTooExpensive(name, cost, missing));
+    }
+    let reserve = app.config.min_reserve;
+    if app.code_lines.0 - (cost as f64) < reserve as f64 {
+        return Err(ClidleError::ReserveBreached(name, cost, reserve));
+    }
+    if let Some(fraction) = app.config.budget_fraction {
+        let budget = budget(app, fraction);
+        if cost > budget {
+            return Err(ClidleError::OverBudget(name, cost, budget));
+        }
+    }
+    app.code_lines -= Lines::from_u64(cost);
+    app.unlocked_items.insert(id);
+    app.log
+        .push(tf(Msg::ItemUnlocked, app.lang, &[&name, &cost]));
+    Ok(())
+}
+
+/// The item name typed in `input` and how many of it to buy.
+///
+/// ## Errors
+///
+/// See `parse_trade` and `command_count`.
+fn buy_count(app: &App, input: &str) -> Result<(String, f64), ClidleError> {
+    let command = parse_trade(Verb::Buy, input)?;
+    let count = command_count(app, &command)?;
+    Ok((command.item, count))
+}
+
+/// What the game would look like after buying what's typed, without buying it.
+struct Preview {
+    name: String,
+    count: f64,
+    lines: (Lines, Lines),
+    cps: (f64, f64),
+    /// seconds until another one is affordable after the purchase, none if never
+    next_in: Option<f64>,
+    /// percent taken off by `bulk_discounts`
+    discount: Option<f64>,
+}
+
+/// Preview the purchase typed in buy mode, none if it couldn't be bought.
+///
+/// Works on a copy of the owned items, the game itself is left untouched.
+fn preview_buy(app: &App) -> Option<Preview> {
+    let input = match &app.input_mode {
+        InputMode::Buy => app.input.clone(),
+        InputMode::Spinner { item } => format!("{item} {}", app.buy_quantity),
+        _ => return None,
+    };
+    let (item, count) = buy_count(app, &input).ok()?;
+    let transaction = plan_buy(app, item, count).ok()?;
+    let mut owned = app.owned_items.clone();
+    owned.insert(transaction.item_id, transaction.owned_after);
+    let lines_after = app.code_lines + transaction.lines_delta;
+    let cps_after = app.cps_with(&owned);
+    let cost = price_at(
+        app.item(transaction.item_id).cost,
+        cps_after,
+        app.config.dynamic_cost,
+    );
+    let next_in = if lines_after.can_pay(cost, app.config.affordability) {
+        Some(0.)
+    } else {
+        (cps_after > 0.).then(|| (cost as f64 - lines_after.0) / cps_after)
+    };
+    Some(Preview {
+        name: transaction.name,
+        count,
+        lines: (app.code_lines, lines_after),
+        cps: (app.total_cps(), cps_after),
+        next_in,
+        discount: bulk_discount(app, count),
+    })
+}
+
+/// Cancel the last purchase, getting back all the code lines paid.
+///
+/// The lifetime purchase counter is left as it is.
+///
+/// ## Errors
+///
+/// `ClidleError::ForbiddenInHardcore` in hardcore mode,
+/// `ClidleError::NoPurchaseToUndo` if there is no purchase left in the history,
+/// or `ClidleError::SellingItemNotOwned` if some of the bought items were sold since.
+fn undo_purchase(app: &mut App) -> Result<(), ClidleError> {
+    if app.config.hardcore {
+        return Err(ClidleError::ForbiddenInHardcore);
+    }
+    let purchase = app.history.last().ok_or(ClidleError::NoPurchaseToUndo)?;
+    let name = app.item(purchase.item_id).name.clone();
+    let owned = app.owned_count(purchase.item_id);
+    let owned_after = remove_count(owned, purchase.count)
+        .ok_or_else(|| ClidleError::SellingItemNotOwned(name.clone(), owned, purchase.count))?;
+    let transaction = Transaction {
+        item_id: purchase.item_id,
+        name,
+        count: purchase.count,
+        owned_after,
+        lines_delta: Lines::from_u64(purchase.price),
+    };
+    app.apply(&transaction);
+    app.history.pop_last();
+    app.log.push(tf(
+        Msg::Undone,
+        app.lang,
+        &[&format_count(transaction.count), &transaction.name],
+    ));
+    Ok(())
+}
+
+/// Commits a prestige gives for `lines` produced during the game.
+///
+/// The first commit takes `prestige_lines` lines, then it grows like the square root:
+/// four times as many lines for twice as many commits.
+fn prestige_commits(lines: Lines, prestige_lines: u64) -> u64 {
+    let ratio = lines.0 / prestige_lines.max(1) as f64;
+    if ratio.is_nan() || ratio < 0. {
+        return 0;
+    }
+    // `as` sature pour les nombres de lignes démesurés
+    ratio.sqrt().floor() as u64
+}
+
+/// Mark `name` as favorite, or unmark it if it already is.
+///
+/// ## Errors
+///
+/// `ClidleError::BuyingItemNotKnown` if there is no such item.
+fn toggle_favorite(app: &mut App, name: &str) -> Result<(), ClidleError> {
+    let name = name.trim();
+    let Some(item) = app.find_item(name) else {
+        return Err(ClidleError::BuyingItemNotKnown(
+            name.to_string(),
+            close_names(app, name),
+        ));
+    };
+    let (id, name) = (item.id, item.name.clone());
+    let message = if app.favorites.remove(&id) {
+        Msg::FavoriteRemoved
+    } else {
+        app.favorites.insert(id);
+        Msg::FavoriteAdded
+    };
+    app.log.push(tf(message, app.lang, &[&name]));
+    Ok(())
+}
 
-    let mut messages: Vec<ListItem> = app
+/// Buy one of each favorite item, cheapest first, stopping at the first that fails.
+///
+/// ## Errors
+///
+/// `ClidleError::NoFavorite` without favorites, or the error of the failed purchase.
+fn buy_favorites(app: &mut App) -> Result<(), ClidleError> {
+    let mut favorites: Vec<(u64, String)> = app
         .items_index
         .iter()
-        .map(|item| {
-            // TODO: on peut afficher le prix de chaque item
-            let content = vec![Spans::from(Span::raw(format!(
-                "Buy {}(as {}) producing {:.2} code lines per second",
-                item.long_name, item.name, item.cps
-            )))];
-            ListItem::new(content)
-        })
+        .filter(|item| app.favorites.contains(&item.id))
+        .map(|item| (app.price_of(item), item.name.clone()))
         .collect();
+    if favorites.is_empty() {
+        return Err(ClidleError::NoFavorite);
+    }
+    favorites.sort();
+    for (_, name) in favorites {
+        buy(app, &name, 1.)?;
+    }
+    Ok(())
+}
+
+/// What the comparison shows of an item.
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    name: String,
+    price: u64,
+    /// code lines one more would produce each second
+    cps: f64,
+    /// code lines per second for each thousand lines spent, none for a free item
+    efficiency: Option<f64>,
+    /// see `break_even_secs`
+    break_even: Option<f64>,
+}
+
+/// What the comparison shows of `item_id`, at the current prices and production.
+fn comparison(app: &App, item_id: ItemId) -> Comparison {
+    let item = app.item(item_id);
+    let price = app.price_of(item);
+    let cps = app.unit_cps(item_id);
+    Comparison {
+        name: item.name.clone(),
+        price,
+        cps,
+        efficiency: (price > 0).then(|| cps / price as f64 * 1000.),
+        break_even: break_even_secs(price, cps),
+    }
+}
+
+/// Add `name` to the comparison, in place of the oldest item once there are two.
+///
+/// ## Errors
+///
+/// `ClidleError::BuyingItemNotKnown` if there is no such item.
+fn compare(app: &mut App, name: &str) -> Result<(), ClidleError> {
+    let name = name.trim();
+    let Some(item) = app.find_item(name) else {
+        return Err(ClidleError::BuyingItemNotKnown(
+            name.to_string(),
+            close_names(app, name),
+        ));
+    };
+    let id = item.id;
+    app.compared = match app.compared {
+        [None, _] => [Some(id), None],
+        [first, None] => [first, Some(id)],
+        [_, second] => [second, Some(id)],
+    };
+    Ok(())
+}
+
+/// Buy a prestige upgrade from the config with commits.
+///
+/// ## Errors
+///
+/// `ClidleError::UpgradeNotKnown` if there is no such upgrade,
+/// `ClidleError::UpgradeAlreadyBought` if it's already bought, or
+/// `ClidleError::NotEnoughCommits` if it's too expensive.
+fn buy_upgrade(app: &mut App, name: &str) -> Result<(), ClidleError> {
+    let name = name.trim();
+    let upgrade: &PrestigeUpgrade = app
+        .config
+        .prestige_upgrades
+        .iter()
+        .find(|upgrade| upgrade.name == name)
+        .ok_or_else(|| ClidleError::UpgradeNotKnown(name.to_string()))?;
+    if app.prestige_upgrades.contains(&upgrade.name) {
+        return Err(ClidleError::UpgradeAlreadyBought(upgrade.name.clone()));
+    }
+    if app.commits < upgrade.cost {
+        return Err(ClidleError::NotEnoughCommits(
+            upgrade.name.clone(),
+            upgrade.cost,
+            app.commits,
+        ));
+    }
+    app.commits -= upgrade.cost;
+    let name = upgrade.name.clone();
+    app.prestige_upgrades.push(name.clone());
+    app.log.push(tf(
+        Msg::UpgradePurchased,
+        app.lang,
+        &[&name, &app.prestige_multiplier()],
+    ));
+    app.ring = app.config.bell;
+    Ok(())
+}
+
+/// Buy the next click tier from the config, making `c` write more lines.
+///
+/// ## Errors
+///
+/// `ClidleError::NoClickTierLeft` once all tiers are bought, or
+/// `ClidleError::ClickTierTooExpensive` if you can't afford the next one.
+fn upgrade_click(app: &mut App) -> Result<(), ClidleError> {
+    let tier = app
+        .config
+        .click_tiers
+        .get(app.click_tier)
+        .ok_or(ClidleError::NoClickTierLeft)?;
+    if app.code_lines.0.is_nan() {
+        return Err(ClidleError::LinesNotANumber);
+    }
+    if !app.can_afford(tier.cost) {
+        return Err(ClidleError::ClickTierTooExpensive(
+            tier.cost,
+            app.code_lines
+                .missing_for(tier.cost, app.config.affordability),
+        ));
+    }
+    app.code_lines -= Lines::from_u64(tier.cost);
+    app.click_tier += 1;
+    app.log.push(tf(
+        Msg::ClickUpgraded,
+        app.lang,
+        &[&app.click_tier, &app.click_power()],
+    ));
+    app.ring = app.config.bell;
+    Ok(())
+}
+
+/// What to type to buy the last purchase again.
+fn rebuy_input(app: &App) -> Option<String> {
+    let purchase = app.history.last()?;
+    Some(format!(
+        "{} {}",
+        app.item(purchase.item_id).name,
+        purchase.count
+    ))
+}
+
+/// Replace the current game by a save exported with `--export`.
+///
+/// ## Errors
+///
+/// `ClidleError::ForbiddenInIronman` in ironman mode, or
+/// `ClidleError::ImportInvalid` if `code` isn't a save of these items.
+fn import_save(app: &mut App, code: &str) -> Result<(), ClidleError> {
+    if app.config.ironman {
+        return Err(ClidleError::ForbiddenInIronman);
+    }
+    let save = Save::import(code)?;
+    if let Some(keys) = &save.keys {
+        keys.check()?;
+    }
+    // sans catalogue, les identifiants doivent être ceux des items actuels
+    let unknown = save
+        .owned_items
+        .keys()
+        .find(|item_id| item_id.0 >= app.items_index.len());
+    if let (true, Some(item_id)) = (save.catalog.is_empty(), unknown) {
+        return Err(ClidleError::ImportInvalid(format!(
+            "unknown item {}",
+            item_id.0
+        )));
+    }
+    app.restore(save);
+    // comme pour une nouvelle partie, rien de l'ancienne ne doit s'annuler ni compter
+    app.history = PurchaseHistory::new(app.config.history_depth);
+    app.owned_samples = vec![Samples::new(OWNED_SAMPLES); app.items_index.len()];
+    app.stats = SessionStats::new(app.clock.now());
+    app.log.push(t(Msg::SaveImported, app.lang).to_string());
+    Ok(())
+}
+
+/// Sell an item you own, getting back half of its cost.
+///
+/// `item` may be the item name or an alias from the config, optionally
+/// followed by a quantity (see `parse_trade`).
+///
+/// ## Errors
+///
+/// See `parse_trade`, `command_count` and `plan_sell`.
+fn sell_item(app: &mut App, item: String) -> Result<(), ClidleError> {
+    trade(app, parse_trade(Verb::Sell, &item)?)
+}
+
+/// Sell `count` of `item` right away.
+///
+/// ## Errors
+///
+/// See `plan_sell`.
+fn sell(app: &mut App, item: &str, count: f64) -> Result<(), ClidleError> {
+    let transaction = plan_sell(app, item.to_string(), count)?;
+    app.apply(&transaction);
+    app.log.push(tf(
+        Msg::Sold,
+        app.lang,
+        &[&format_count(transaction.count), &transaction.name],
+    ));
+    Ok(())
+}
 
-    if let Err(error) = app.error.as_ref() {
-        messages.push(ListItem::new(Spans::from(Span::raw(format!(
-            "Error: {error}",
-        )))))
+/// `sell`, unless it sells the last copies and `confirm_last_sell` asks first.
+///
+/// ## Errors
+///
+/// See `plan_sell`.
+fn sell_checked(app: &mut App, item: &str, count: f64) -> Result<(), ClidleError> {
+    if app.config.confirm_last_sell {
+        // une vente impossible s'arrête sur son erreur, pas besoin de confirmer
+        if let Ok(transaction) = plan_sell(app, item.to_string(), count) {
+            if transaction.owned_after <= COUNT_EPSILON {
+                let back_to = Box::new(std::mem::replace(&mut app.input_mode, InputMode::Normal));
+                app.input_mode = InputMode::ConfirmSell {
+                    item: item.to_string(),
+                    count,
+                    back_to,
+                };
+                return Ok(());
+            }
+        }
     }
+    sell(app, item, count)
+}
 
-    let messages =
-        List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
-    f.render_widget(messages, chunks[3]);
+/// The item adding the most code lines per second for its price when owning `owned`,
+/// with that price and what would be owned once it's bought.
+///
+/// Only items whose price is `allowed` and that would raise the production count,
+/// synergies, `max` and `dynamic_cost` included. Ties go to the first item of the
+/// catalog, so the pick doesn't flicker.
+fn best_value(
+    app: &App,
+    owned: &HashMap<ItemId, f64>,
+    allowed: impl Fn(u64) -> bool,
+) -> Option<(ItemId, u64, HashMap<ItemId, f64>)> {
+    let cps = app.cps_with(owned);
+    app.items_index
+        .iter()
+        .filter(|item| {
+            let count = owned.get(&item.id).copied().unwrap_or(0.);
+            item.max.is_none_or(|max| count + 1. <= max as f64)
+        })
+        .filter(|item| app.unlock_cost(item).is_none())
+        .filter_map(|item| {
+            let price = price_at(item.cost, cps, app.config.dynamic_cost);
+            if !allowed(price) {
+                return None;
+            }
+            let mut with_one = owned.clone();
+            *with_one.entry(item.id).or_insert(0.) += 1.;
+            let gain = app.cps_with(&with_one) - cps;
+            // un item gratuit est toujours le plus rentable
+            (gain > 0.).then(|| (item.id, price, gain / price.max(1) as f64, with_one))
+        })
+        .max_by(|(id_a, _, ratio_a, _), (id_b, _, ratio_b, _)| {
+            ratio_a.total_cmp(ratio_b).then(id_b.cmp(id_a))
+        })
+        .map(|(item_id, price, _, with_one)| (item_id, price, with_one))
+}
+
+/// The affordable item worth buying the most right now, see `best_value`.
+///
+/// None when nothing affordable would raise the production.
+fn best_purchase(app: &App) -> Option<ItemId> {
+    best_value(app, &app.owned_items, |price| app.can_afford(price)).map(|(item_id, ..)| item_id)
+}
+
+/// Most purchases `double_cps_advice` simulates before giving up.
+const MAX_ADVICE_STEPS: usize = 1000;
+
+/// Purchases that would about double the production.
+struct Advice {
+    /// (item, count) in the order they are first bought
+    purchases: Vec<(ItemId, u64)>,
+    /// code lines they cost altogether
+    cost: u64,
+}
+
+/// A planning hint: what to buy to at least double the current production.
+///
+/// Greedy rather than optimal, it buys one item at a time, each time the one giving
+/// the most code lines per second for its price, until the production doubled.
+/// Synergies, `max` and `dynamic_cost` are taken into account. None when nothing is
+/// produced yet, since there is nothing to double, or when it takes too many purchases.
+fn double_cps_advice(app: &App) -> Option<Advice> {
+    let cps = app.total_cps();
+    if !cps.is_finite() || cps <= 0. {
+        return None;
+    }
+    let mut owned = app.owned_items.clone();
+    let mut advice = Advice {
+        purchases: Vec::new(),
+        cost: 0,
+    };
+    for _ in 0..MAX_ADVICE_STEPS {
+        if app.cps_with(&owned) >= 2. * cps {
+            return Some(advice);
+        }
+        let (item_id, price, with_one) = best_value(app, &owned, |_| true)?;
+        owned = with_one;
+        advice.cost = advice.cost.saturating_add(price);
+        match advice.purchases.iter_mut().find(|(id, _)| *id == item_id) {
+            Some((_, count)) => *count += 1,
+            None => advice.purchases.push((item_id, 1)),
+        }
+    }
+    None
+}
+
+/// The owned item producing the least code lines, none if nothing is owned.
+fn worst_performer(app: &App) -> Option<ItemId> {
+    app.production_ranking().last().map(|(item_id, _)| *item_id)
+}
+
+/// Sell one of the owned item producing the least, to spend its lines elsewhere.
+///
+/// Less than one is left with `fractional_items`, what's left is sold.
+fn sell_worst(app: &mut App) -> Result<(), ClidleError> {
+    match worst_performer(app) {
+        Some(item_id) => {
+            let count = app.owned_count(item_id).min(1.);
+            sell_checked(app, &app.item(item_id).name.clone(), count)
+        }
+        None => {
+            app.log.push(t(Msg::NothingToSell, app.lang).to_string());
+            Ok(())
+        }
+    }
+}
+
+/// Handles inputs if it's successful you get a GameState if not you may end up with
+/// an IO error.
+fn handle_input(app: &mut App, input: &mut dyn Input) -> io::Result<GameState> {
+    if let Some(key) = input.read()? {
+        app.last_activity = app.clock.now();
+        if let Some(recording) = app.recording.as_mut() {
+            recording.record_key(&key);
+        }
+        return Ok(handle_key(app, key));
+    }
+    Ok(GameState::Noop)
+}
+
+/// What a key press does to the game, live or replayed.
+fn handle_key(app: &mut App, key: KeyEvent) -> GameState {
+    if is_interrupt(&key) {
+        return GameState::Interrupt;
+    }
+    // on peut tourner les pages des items dans tous les modes
+    let page_count = page_count(app.listed_items().len(), app.config.page_size);
+    match key.code {
+        KeyCode::PageDown => app.page = (app.page + 1).min(page_count - 1),
+        KeyCode::PageUp => app.page = app.page.saturating_sub(1),
+        KeyCode::Home => app.page = 0,
+        KeyCode::End => app.page = page_count - 1,
+        _ => {}
+    }
+    // aucune touche du jeu ne se joue avec Ctrl ou Alt, Ctrl-x n'est pas x
+    if key
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        unrecognized_key(app, &key);
+        return GameState::Noop;
+    }
+    match app.input_mode {
+        InputMode::Normal => match key.code {
+            KeyCode::Char(c) if c == app.keys.buy => {
+                app.input_mode = InputMode::Buy;
+            }
+            KeyCode::Char(c) if c == app.keys.code => {
+                app.produce(Lines(app.click_power()));
+            }
+            KeyCode::Char(c) if c == app.keys.sell && app.config.hardcore => {
+                app.report(Err(ClidleError::ForbiddenInHardcore));
+            }
+            KeyCode::Char(c) if c == app.keys.sell => {
+                app.input_mode = InputMode::Sell;
+            }
+            KeyCode::Char(c) if c == app.keys.quit => {
+                return GameState::Quit;
+            }
+            KeyCode::Char(c) if c == app.keys.search => {
+                app.search_query.clear();
+                app.input_mode = InputMode::Search;
+            }
+            KeyCode::Char(c) if c == app.keys.undo => {
+                return GameState::Undo;
+            }
+            KeyCode::Char(c) if c == app.keys.reset => {
+                app.input_mode = InputMode::ConfirmReset;
+            }
+            KeyCode::Char(c) if c == app.keys.upgrade_click => {
+                return GameState::UpgradeClick;
+            }
+            KeyCode::Char(c) if c == app.keys.pause => {
+                app.paused = !app.paused;
+            }
+            KeyCode::Char(c) if c == app.keys.auto_code => {
+                app.auto_code = !app.auto_code;
+                let message = if app.auto_code {
+                    Msg::AutoCodeOn
+                } else {
+                    Msg::AutoCodeOff
+                };
+                app.log.push(t(message, app.lang).to_string());
+            }
+            KeyCode::Char(c) if c == app.keys.sell_worst => {
+                return GameState::SellWorst;
+            }
+            KeyCode::Char(c) if c == app.keys.layout => {
+                app.layout = app.layout.next();
+            }
+            KeyCode::Char(c) if c == app.keys.focus => {
+                app.focus_mode = !app.focus_mode;
+            }
+            KeyCode::Char(c) if c == app.keys.import => {
+                app.input_mode = InputMode::Import;
+            }
+            KeyCode::Char(c) if c == app.keys.command => {
+                app.input_mode = InputMode::Command;
+            }
+            KeyCode::Char(c) if c == app.keys.prestige => {
+                app.input_mode = InputMode::ConfirmPrestige;
+            }
+            KeyCode::Char(c) if c == app.keys.shop => {
+                app.input_mode = InputMode::Shop;
+            }
+            KeyCode::Char(c) if c == app.keys.favorite => {
+                app.input_mode = InputMode::Favorite;
+            }
+            KeyCode::Char(c) if c == app.keys.favorites_only => {
+                app.favorites_only = !app.favorites_only;
+            }
+            KeyCode::Char(c) if c == app.keys.buy_favorites => {
+                return GameState::BuyFavorites;
+            }
+            KeyCode::Char(c) if c == app.keys.compare => {
+                app.input_mode = InputMode::Compare;
+            }
+            KeyCode::Char(c) if c == app.keys.rebuy => {
+                if let Some(input) = rebuy_input(app) {
+                    return GameState::BuyItem(input);
+                }
+            }
+            KeyCode::Char('+') => {
+                app.forecast_secs = (app.forecast_secs * 2).min(MAX_FORECAST_SECS);
+            }
+            KeyCode::Char('-') => {
+                app.forecast_secs = (app.forecast_secs / 2).max(1);
+            }
+            KeyCode::Char(c) if c == app.keys.language => {
+                app.lang = app.lang.next();
+            }
+            KeyCode::F(12) => {
+                if let Some(debug) = app.debug.as_mut() {
+                    debug.visible = !debug.visible;
+                }
+            }
+            KeyCode::F(10) if app.debug.is_some() => {
+                let message = if app.time_scale == 1. {
+                    app.time_scale = FAST_FORWARD;
+                    tf(Msg::FastForward, app.lang, &[&FAST_FORWARD])
+                } else {
+                    app.time_scale = 1.;
+                    t(Msg::NormalSpeed, app.lang).to_string()
+                };
+                app.log.push(message);
+            }
+            KeyCode::F(11) if app.debug.is_some() => {
+                let report = match app.check_invariants() {
+                    Ok(()) => t(Msg::InvariantsHold, app.lang).to_string(),
+                    Err(reason) => tf(Msg::InvariantBroken, app.lang, &[&reason]),
+                };
+                app.log.push(report);
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        // En mode achat ou vente, `c` est une lettre comme une autre du nom de l'item,
+        // sauf avec `code_key_while_typing` où il code tant que la saisie est vide.
+        InputMode::Buy | InputMode::Sell
+            if key.code == KeyCode::Char(app.keys.code)
+                && app.input.is_empty()
+                && app.config.code_key_while_typing =>
+        {
+            app.produce(Lines(app.click_power()));
+        }
+        InputMode::Buy => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            // un Entrée de trop, la saisie vient d'être envoyée
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                return GameState::BuyItem(app.input.drain(..).collect());
+            }
+            KeyCode::Tab if !app.input.trim().is_empty() => {
+                let typed = app.input.trim();
+                match app.find_item(typed) {
+                    Some(item) => {
+                        app.input_mode = InputMode::Spinner {
+                            item: item.name.clone(),
+                        };
+                        app.buy_quantity = 1;
+                        app.input.clear();
+                    }
+                    None => {
+                        let error = ClidleError::BuyingItemNotKnown(
+                            typed.to_string(),
+                            close_names(app, typed),
+                        );
+                        app.report(Err(error));
+                    }
+                }
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Spinner { ref item } => match key.code {
+            KeyCode::Char('+') | KeyCode::Up => {
+                app.buy_quantity = spin(app, item, 1);
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                app.buy_quantity = spin(app, item, -1);
+            }
+            KeyCode::Enter => {
+                let input = format!("{item} {}", app.buy_quantity);
+                app.input_mode = InputMode::Buy;
+                return GameState::BuyItem(input);
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Buy;
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Import => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter => {
+                app.input_mode = InputMode::Normal;
+                return GameState::Import(app.input.drain(..).collect());
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+                app.input.clear();
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Command => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                let command: String = app.input.drain(..).collect();
+                match parse_macro(&command) {
+                    Ok(mut actions) if actions.len() == 1 => {
+                        return run_action(app, actions.remove(0));
+                    }
+                    Ok(actions) => return GameState::Macro(actions),
+                    Err(error) => app.report(Err(error)),
+                }
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+                app.input.clear();
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Sell => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            // un Entrée de trop, la saisie vient d'être envoyée
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                return GameState::SellItem(app.input.drain(..).collect());
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Search => match key.code {
+            KeyCode::Char(c) => {
+                app.search_query.push(c);
+            }
+            KeyCode::Backspace => {
+                app.search_query.pop();
+            }
+            // sans recherche tous les items correspondent, rien n'a encore été choisi
+            KeyCode::Enter if app.search_query.trim().is_empty() => {}
+            KeyCode::Enter => {
+                app.input_mode = InputMode::Normal;
+                let query: String = app.search_query.drain(..).collect();
+                // sans résultat, on laisse buy_item signaler que l'item n'existe pas
+                let item = match filter_items(&app.items_index, &query).first() {
+                    Some(top_match) => top_match.name.clone(),
+                    None => query,
+                };
+                return GameState::BuyItem(item);
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::ConfirmReset => {
+            app.input_mode = InputMode::Normal;
+            // toute autre touche annule
+            if key.code == KeyCode::Char('y') {
+                return GameState::Reset;
+            }
+        }
+        InputMode::ConfirmPrestige => {
+            app.input_mode = InputMode::Normal;
+            // toute autre touche annule
+            if key.code == KeyCode::Char('y') {
+                return GameState::Prestige;
+            }
+        }
+        InputMode::Shop => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                return GameState::BuyUpgrade(app.input.drain(..).collect());
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+                app.input.clear();
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Favorite => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                return GameState::ToggleFavorite(app.input.drain(..).collect());
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+                app.input.clear();
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::Compare => match key.code {
+            KeyCode::Char(c) => {
+                app.input.push(c);
+            }
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter if app.input.trim().is_empty() => {}
+            KeyCode::Enter => {
+                return GameState::Compare(app.input.drain(..).collect());
+            }
+            KeyCode::Esc => {
+                app.input_mode = InputMode::Normal;
+                app.input.clear();
+                app.compared = [None; 2];
+            }
+            _ => unrecognized_key(app, &key),
+        },
+        InputMode::ConfirmUnlock { .. } => {
+            let mode = std::mem::replace(&mut app.input_mode, InputMode::Normal);
+            if let InputMode::ConfirmUnlock { item, back_to, .. } = mode {
+                // on retourne là d'où on venait, et toute autre touche annule
+                app.input_mode = *back_to;
+                if key.code == KeyCode::Char('y') {
+                    return GameState::Unlock(item);
+                }
+            }
+        }
+        InputMode::ConfirmBuy { .. } => {
+            let mode = std::mem::replace(&mut app.input_mode, InputMode::Normal);
+            if let InputMode::ConfirmBuy {
+                item,
+                count,
+                back_to,
+                ..
+            } = mode
+            {
+                app.input_mode = *back_to;
+                if key.code == KeyCode::Char('y') {
+                    return GameState::ConfirmBuy { item, count };
+                }
+            }
+        }
+        InputMode::ConfirmSell { .. } => {
+            let mode = std::mem::replace(&mut app.input_mode, InputMode::Normal);
+            if let InputMode::ConfirmSell {
+                item,
+                count,
+                back_to,
+            } = mode
+            {
+                app.input_mode = *back_to;
+                if key.code == KeyCode::Char('y') {
+                    return GameState::ConfirmSell { item, count };
+                }
+            }
+        }
+    }
+    GameState::Noop
+}
+
+/// Something to do, typed as a command in command mode.
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// `buy <item> [count]` or `sell <item> [count]`, see `parse_command`
+    Trade(Command),
+    Code,
+    Upgrade,
+    Undo,
+    Pause,
+    Quit,
+}
+
+/// Read a command typed in command mode, like `buy dev 10` or `code`.
+///
+/// ## Errors
+///
+/// `ClidleError::UnknownCommand` if it doesn't start with a known verb,
+/// see `parse_command` for purchases and sales.
+fn parse_action(command: &str) -> Result<Action, ClidleError> {
+    let command = command.trim();
+    let (verb, rest) = command.split_once(' ').unwrap_or((command, ""));
+    let rest = rest.trim();
+    let action = match (verb.to_lowercase().as_str(), rest.is_empty()) {
+        ("buy" | "sell", _) => Action::Trade(parse_command(command)?),
+        ("code", true) => Action::Code,
+        ("upgrade", true) => Action::Upgrade,
+        ("undo", true) => Action::Undo,
+        ("pause", true) => Action::Pause,
+        ("quit", true) => Action::Quit,
+        _ => return Err(ClidleError::UnknownCommand(command.to_string())),
+    };
+    Ok(action)
+}
+
+/// Read commands separated by `;`, like `buy dev 5; buy git 2; code`.
+///
+/// Empty steps are skipped, so a trailing `;` is fine.
+///
+/// ## Errors
+///
+/// `ClidleError::MacroStepFailed` with the error of the first step that isn't a command,
+/// or just that error when there is a single step.
+fn parse_macro(input: &str) -> Result<Vec<Action>, ClidleError> {
+    let steps: Vec<&str> = input
+        .split(';')
+        .filter(|step| !step.trim().is_empty())
+        .collect();
+    if let [step] = steps[..] {
+        return Ok(vec![parse_action(step)?]);
+    }
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            parse_action(step)
+                .map_err(|error| ClidleError::MacroStepFailed(index + 1, Box::new(error)))
+        })
+        .collect()
+}
+
+/// Do each action of a macro in turn, stopping at the first that fails or that
+/// asks the player to confirm, the question left open.
+///
+/// False if one of them quits the game.
+///
+/// ## Errors
+///
+/// `ClidleError::MacroStepFailed` with the error of the failing step, or
+/// `ClidleError::MacroStepUnconfirmed` if steps are left after a confirmation.
+fn run_macro(app: &mut App, actions: Vec<Action>) -> Result<bool, ClidleError> {
+    // une erreur d'avant la macro ne doit pas passer pour celle d'une étape
+    app.error = Ok(());
+    let steps = actions.len();
+    for (index, action) in actions.into_iter().enumerate() {
+        let state = run_action(app, action);
+        if !play(app, state) {
+            return Ok(false);
+        }
+        if let Err(error) = std::mem::replace(&mut app.error, Ok(())) {
+            return Err(ClidleError::MacroStepFailed(index + 1, Box::new(error)));
+        }
+        // les étapes suivantes se feraient avant la réponse, qui doit rester la dernière
+        let confirming = matches!(
+            app.input_mode,
+            InputMode::ConfirmBuy { .. }
+                | InputMode::ConfirmSell { .. }
+                | InputMode::ConfirmUnlock { .. }
+        );
+        if confirming && index + 1 < steps {
+            return Err(ClidleError::MacroStepUnconfirmed(index + 1));
+        }
+    }
+    Ok(true)
+}
+
+/// Do a typed command, like the keys of normal mode would.
+fn run_action(app: &mut App, action: Action) -> GameState {
+    match action {
+        Action::Trade(command) => return GameState::Trade(command),
+        Action::Code => app.produce(Lines(app.click_power())),
+        Action::Upgrade => return GameState::UpgradeClick,
+        Action::Undo => return GameState::Undo,
+        Action::Pause => app.paused = !app.paused,
+        Action::Quit => return GameState::Quit,
+    }
+    GameState::Noop
+}
+
+/// Tell that the current mode does nothing with `key`, only with `--debug`.
+fn unrecognized_key(app: &mut App, key: &KeyEvent) {
+    // les pages se tournent dans tous les modes, avant le reste de `handle_key`
+    let paging = matches!(
+        key.code,
+        KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+    );
+    if app.debug.is_none() || paging {
+        return;
+    }
+    let mut name = key_name(key.code).unwrap_or_else(|| format!("{:?}", key.code));
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        name.insert_str(0, "alt+");
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        name.insert_str(0, "ctrl+");
+    }
+    app.log.push(tf(Msg::UnrecognizedKey, app.lang, &[&name]));
+}
+
+/// Whether `key` is Ctrl-C.
+///
+/// In raw mode the terminal doesn't send SIGINT anymore, so Ctrl-C arrives
+/// as a key and quits the game like `q`, wherever you are.
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// The forecast doesn't look further than a year ahead.
+const MAX_FORECAST_SECS: u64 = 365 * 24 * 3600;
+
+/// The code lines you'll have in `secs` seconds if production stays at `cps`.
+fn forecast(lines: Lines, cps: f64, secs: u64) -> Lines {
+    (lines + Lines(cps * secs as f64)).max(Lines::ZERO)
+}
+
+/// How many pages of `page_size` items are needed for `len` items, at least one.
+fn page_count(len: usize, page_size: usize) -> usize {
+    len.div_ceil(page_size.max(1)).max(1)
+}
+
+/// Indices of the items shown on `page`, which is clamped to the last page.
+fn page_range(len: usize, page_size: usize, page: usize) -> std::ops::Range<usize> {
+    let page_size = page_size.max(1);
+    let page = page.min(page_count(len, page_size) - 1);
+    let start = page * page_size;
+    start..(start + page_size).min(len)
+}
+
+/// Which way the code lines changed sharply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flash {
+    Gain,
+    Loss,
+}
+
+/// The flash for code lines going from `before` to `after`, none unless they changed
+/// by at least `FLASH_RATIO` of the biggest of both and `FLASH_MIN_LINES`.
+fn flash_for(before: Lines, after: Lines) -> Option<Flash> {
+    let delta = (after - before).0;
+    let big = delta.abs() >= FLASH_MIN_LINES && delta.abs() >= FLASH_RATIO * before.max(after).0;
+    match (big, delta > 0.) {
+        (false, _) => None,
+        (true, true) => Some(Flash::Gain),
+        (true, false) => Some(Flash::Loss),
+    }
+}
+
+/// Whether the player was away long enough at `now` to quit, with `idle_quit_secs`.
+fn idle_expired(last_activity: Instant, now: Instant, idle_quit_secs: Option<u64>) -> bool {
+    idle_quit_secs.is_some_and(|secs| {
+        now.saturating_duration_since(last_activity) >= Duration::from_secs(secs)
+    })
+}
+
+/// Code lines produced per second of play on average, none before the first second.
+fn average_rate(lines: Lines, played_secs: u64) -> Option<f64> {
+    (played_secs > 0).then(|| lines.0 / played_secs as f64)
+}
+
+/// Highest factor `dynamic_cost` can multiply prices by.
+const MAX_DYNAMIC_COST_FACTOR: f64 = 10.;
+
+/// The price of an item costing `cost` in the catalog, when producing `total_cps`.
+///
+/// With `dynamic_cost` prices grow with the production to keep the game
+/// challenging: `cost * sqrt(1 + total_cps / 100)`, rounded up, so producing a
+/// hundred lines per second makes everything about 41 % more expensive. The factor
+/// never goes above `MAX_DYNAMIC_COST_FACTOR` so prices can't run away.
+fn price_at(cost: u64, total_cps: f64, dynamic_cost: bool) -> u64 {
+    if !dynamic_cost || total_cps.is_nan() || total_cps <= 0. {
+        return cost;
+    }
+    let factor = (1. + total_cps / 100.).sqrt().min(MAX_DYNAMIC_COST_FACTOR);
+    // `as` sature pour les prix trop grands
+    (cost as f64 * factor).ceil() as u64
+}
+
+/// Seconds of its own production for an item bought at `price` to pay for itself.
+///
+/// `None` if it never does, when it produces nothing or costs lines.
+/// A free item pays for itself right away, whatever it produces.
+fn break_even_secs(price: u64, cps: f64) -> Option<f64> {
+    if price == 0 {
+        return Some(0.);
+    }
+    (cps > 0.).then(|| price as f64 / cps)
+}
+
+/// When `item` pays for itself, as the item list and the inspected item tell it.
+fn break_even_label(app: &App, item: &Item) -> String {
+    let price = app.price_of(item);
+    match (
+        app.unlock_cost(item),
+        break_even_secs(price, app.unit_cps(item.id)),
+    ) {
+        (Some(cost), _) => tf(Msg::Locked, app.lang, &[&cost]),
+        _ if price == 0 => t(Msg::Free, app.lang).to_string(),
+        (None, Some(secs)) => tf(Msg::PaysOffIn, app.lang, &[&format!("{secs:.0}")]),
+        (None, None) => t(Msg::NeverPaysOff, app.lang).to_string(),
+    }
+}
+
+/// How `item` reads in the item list: the `> ` of the `best` pick, the `* ` of the
+/// favorites, its icon in `icon_width` columns if any item has one, then its `item_line`.
+fn item_row(app: &App, item: &Item, best: Option<ItemId>, icon_width: Option<usize>) -> String {
+    let mut row = String::new();
+    // les items sans marque ont une colonne vide, pour rester alignés sur les autres
+    if best.is_some() {
+        row += if best == Some(item.id) { "> " } else { "  " };
+    }
+    if !app.favorites.is_empty() {
+        row += if app.favorites.contains(&item.id) {
+            "* "
+        } else {
+            "  "
+        };
+    }
+    if let Some(width) = icon_width {
+        row += &icon_cell(item.icon.as_deref().unwrap_or_default(), width);
+    }
+    row + &item_line(app, item)
+}
+
+/// `icon` padded to `width` columns and a space, so the names after it line up
+/// even with wide glyphs or items without icon.
+fn icon_cell(icon: &str, width: usize) -> String {
+    let padding = width.saturating_sub(icon.width());
+    format!("{icon}{} ", " ".repeat(padding))
+}
+
+/// Where the code lines are heading with the current production.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outlook {
+    Growing,
+    Stable,
+    /// seconds until no code line is left, items costing more than they produce
+    Doomed(f64),
+}
+
+/// The outlook of owning `lines` and producing `cps` each second.
+///
+/// A production that isn't a number counts as stable, the error is shown elsewhere.
+fn outlook(lines: Lines, cps: f64) -> Outlook {
+    if cps > 0. {
+        Outlook::Growing
+    } else if cps < 0. {
+        Outlook::Doomed(lines.0.max(0.) / -cps)
+    } else {
+        Outlook::Stable
+    }
+}
+
+/// How much an owned item weighs in the production, to color it in the owned list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Contribution {
+    /// at least `MAJOR_SHARE` of the production
+    Major,
+    /// at least `MINOR_SHARE` of the production
+    Minor,
+    Negligible,
+}
+
+const MAJOR_SHARE: f64 = 0.25;
+const MINOR_SHARE: f64 = 0.05;
+
+/// The tier of an item producing `item_cps` out of `total_cps`.
+///
+/// None when nothing is produced, every item is then as (un)important as the others.
+/// An item costing lines is negligible.
+fn contribution(item_cps: f64, total_cps: f64) -> Option<Contribution> {
+    if total_cps <= 0. || !total_cps.is_finite() {
+        return None;
+    }
+    let share = item_cps / total_cps;
+    Some(if share >= MAJOR_SHARE {
+        Contribution::Major
+    } else if share >= MINOR_SHARE {
+        Contribution::Minor
+    } else {
+        Contribution::Negligible
+    })
+}
+
+/// The catalog as a text table, one row per item, for `--list-items`.
+///
+/// Columns are padded to their widest cell, in terminal columns.
+fn items_table(items: &[Item]) -> String {
+    let mut rows = vec![["id", "name", "long_name", "cost", "cps"].map(String::from)];
+    rows.extend(items.iter().map(|item| {
+        [
+            item.id.0.to_string(),
+            item.name.clone(),
+            item.long_name.clone(),
+            item.cost.to_string(),
+            item.cps.to_string(),
+        ]
+    }));
+    let widths: Vec<usize> = (0..5)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].width())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.width())))
+            .collect();
+        table += cells.join("  ").trim_end();
+        table.push('\n');
+    }
+    table
+}
+
+/// The owned items as csv for a spreadsheet, in catalog order, for `--owned-csv`.
+///
+/// `value` is what selling them all would give back.
+fn owned_csv(app: &App) -> String {
+    let mut csv = String::from("name,count,cps_each,total_cps,value\n");
+    for item in &app.items_index {
+        let Some(count) = app.owned_items.get(&item.id) else {
+            continue;
+        };
+        let cps_each = app.unit_cps(item.id);
+        csv += &format!(
+            "{},{},{},{},{}\n",
+            csv_field(&item.name),
+            count,
+            cps_each,
+            count * cps_each,
+            price_for(item.cost, *count) as f64 / 2.
+        );
+    }
+    csv
+}
+
+/// `field` quoted if it holds a comma, a quote or a line break, quotes doubled.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Replace each `{key}` of `template` by its value in `values`.
+///
+/// Unknown placeholders are kept as they are, so a typo shows up on screen.
+fn fill_template(template: &str, values: &[(&str, &dyn std::fmt::Display)]) -> String {
+    let mut filled = template.to_string();
+    for (key, value) in values {
+        filled = filled.replace(&format!("{{{key}}}"), &value.to_string());
+    }
+    filled
+}
+
+/// How `item` reads in the item list, following its `display_template`, or else the
+/// `item_template` of the config, or else the default line.
+fn item_line(app: &App, item: &Item) -> String {
+    let cps = app.unit_cps(item.id);
+    let break_even = break_even_label(app, item);
+    let template = item
+        .display_template
+        .as_ref()
+        .or(app.config.item_template.as_ref());
+    match template {
+        Some(template) => fill_template(
+            template,
+            &[
+                ("name", &item.name),
+                ("long_name", &item.long_name),
+                ("cps", &format_rate(app, cps)),
+                ("price", &app.price_of(item)),
+                ("owned", &format_count(app.owned_count(item.id))),
+                ("break_even", &break_even),
+            ],
+        ),
+        None => {
+            tf(
+                Msg::ItemLine,
+                app.lang,
+                &[
+                    &item.long_name,
+                    &item.name,
+                    &format_rate(app, cps),
+                    &rate_label(app),
+                ],
+            ) + &break_even
+        }
+    }
+}
+
+/// Items whose name or long name contains `query`, ignoring case, in catalog order.
+fn filter_items<'a>(items: &'a [Item], query: &str) -> Vec<&'a Item> {
+    let query = query.to_lowercase();
+    items
+        .iter()
+        .filter(|item| {
+            item.name.to_lowercase().contains(&query)
+                || item.long_name.to_lowercase().contains(&query)
+        })
+        .collect()
+}
+
+/// contient la boucle de jeu
+///
+/// Ici Terminal est un type générique qui a besoin d'une interface (un trait)
+/// pour fonctionner. Ici en particulier le trait backend permet d'interagir avec le terminal
+/// (dessin, gestion du curseur, etc)
+///
+///
+fn run_app<B: Backend + io::Write>(
+    terminal: &mut Terminal<B>,
+    input: &mut dyn Input,
+    app: &mut App,
+) -> Result<(), Box<dyn Error>> {
+    // le temps d'agrandir le terminal ne compte pas comme du jeu
+    if !wait_for_size(terminal, input, app)? {
+        return Ok(());
+    }
+    // pour vérifier si il faut mettre à jour l'état du jeu
+    let mut last_tick = app.clock.now();
+    // on ne redessine que si quelque chose a changé, et au plus 30 fois par seconde
+    let frame_interval = Duration::from_millis(1000 / 30);
+    // la première image est dessinée tout de suite, avant la première touche
+    let start = app.clock.now();
+    let mut last_draw = start.checked_sub(frame_interval).unwrap_or(start);
+
+    loop {
+        if tick(app, &mut last_tick) {
+            auto_prestige(app);
+            autosave(app);
+        }
+
+        let now = app.clock.now();
+        // le jeu est sauvegardé en sortant, comme avec `q`
+        if idle_expired(app.last_activity, now, app.config.idle_quit_secs) {
+            return Ok(());
+        }
+
+        app.track_flash(now);
+        if app.flash.is_some_and(|(_, until)| now >= until) {
+            app.flash = None;
+            app.dirty = true;
+        }
+
+        if app.highlight_until.is_some_and(|until| now >= until) {
+            app.highlight_until = None;
+            app.dirty = true;
+        }
+
+        if app.dirty && now.saturating_duration_since(last_draw) >= frame_interval {
+            // ici l'argument de la fonction est une closure, une autre fonction anonyme
+            let frame_start = app.clock.now();
+            app.frame += 1;
+            terminal.draw(|f| ui(f, app))?;
+            last_draw = app.clock.now();
+            // le compteur continue de glisser aux frames suivantes
+            app.dirty = app.shown_lines != app.code_lines;
+            if let Some(debug) = app.debug.as_mut() {
+                debug.frame_time = last_draw.saturating_duration_since(frame_start);
+            }
+        }
+        if std::mem::take(&mut app.ring) {
+            ring_bell(terminal.backend_mut())?;
+        }
+
+        // une frame en attente doit être dessinée dès que possible
+        let poll_timeout = if app.dirty {
+            frame_interval.saturating_sub(app.clock.now().saturating_duration_since(last_draw))
+        } else {
+            Duration::from_millis(100)
+        };
+        if let Some(debug) = app.debug.as_mut() {
+            debug.poll_timeout = poll_timeout;
+        }
+
+        // la fonction poll permet de vérifier si un evenement s'est rendu disponible
+        // avant la fin du temps inparti
+        if input.poll(poll_timeout)? {
+            // tout évènement (touche, redimensionnement) peut changer l'affichage
+            app.dirty = true;
+            let state = handle_input(app, input)?;
+            // Ctrl-C veut sortir tout de suite, pas lire un résumé
+            let interrupted = matches!(state, GameState::Interrupt);
+            if !play(app, state) {
+                if app.config.session_summary && !interrupted {
+                    show_summary(terminal, input, app)?;
+                }
+                return Ok(());
+            }
+            // même une touche gérée sans action, comme coder, change la partie
+            autosave(app);
+        }
+    }
+}
+
+/// Write the save right away to `autosave` if the game changed since the last time,
+/// so an ironman game can't go back.
+fn autosave(app: &mut App) {
+    let Some(path) = app.autosave.clone() else {
+        return;
+    };
+    let snapshot = app.snapshot();
+    if app.autosaved.as_ref() == Some(&snapshot) {
+        return;
+    }
+    // une sauvegarde réussie n'efface pas l'erreur de l'action qui vient d'avoir lieu
+    match snapshot.write(&path) {
+        Ok(()) => app.autosaved = Some(snapshot),
+        Err(e) => app.report(Err(ClidleError::SaveFailed(e.to_string()))),
+    }
+}
+
+/// Produce for the time elapsed on the app clock since `last_tick`, once a whole second
+/// went by, and tell whether it did.
+fn tick(app: &mut App, last_tick: &mut Instant) -> bool {
+    let now = app.clock.now();
+    // en pause le temps ne compte pas, pour ne pas tout produire d'un coup à la reprise
+    if app.paused {
+        *last_tick = now;
+    }
+    let elapsed = now.saturating_duration_since(*last_tick);
+    if elapsed < Duration::from_secs(1) {
+        return false;
+    }
+    let before = app.code_lines;
+    // après une mise en veille, tout le retard est crédité d'un coup, dans une limite
+    let away = elapsed.as_secs();
+    let mut secs = away.min(app.config.max_catchup_secs.max(1));
+    if away > SUSPEND_SECS {
+        // le temps d'absence rapporte moins que le jeu actif, arrondi à la seconde
+        secs = (secs as f64 * app.config.offline_rate).round() as u64;
+        app.log.push(tf(Msg::CaughtUp, app.lang, &[&away, &secs]));
+    }
+    // l'avance rapide s'applique après la limite du rattrapage, et la pause
+    // l'arrête comme le reste puisque le temps ne compte plus
+    let secs = (secs as f64 * app.time_scale).round() as u64;
+    app.update(secs);
+    // le rejeu n'a pas besoin de l'avance rapide, les secondes sont déjà multipliées
+    if let Some(recording) = app.recording.as_mut() {
+        recording.events.push(if secs != 1 {
+            RecordedEvent::CatchUp { secs }
+        } else {
+            RecordedEvent::Tick
+        });
+    }
+    if let Some(debug) = app.debug.as_mut() {
+        debug.tick_delta = elapsed;
+        debug.tick_production = (app.code_lines - before).0;
+    }
+    *last_tick = now;
+    app.dirty = true;
+    true
+}
+
+/// Prestige without confirmation once the game reached `auto_prestige_at` code lines,
+/// produced or held.
+///
+/// Called once per tick, and the prestige starts the lines over, so it can't fire twice
+/// in a row. Nothing happens while a prestige wouldn't earn any commit.
+fn auto_prestige(app: &mut App) {
+    let Some(threshold) = app.config.auto_prestige_at else {
+        return;
+    };
+    let reached = app.lifetime_lines.0.max(app.code_lines.0);
+    if reached < threshold || prestige_commits(app.lifetime_lines, app.config.prestige_lines) == 0 {
+        return;
+    }
+    // les commits à gagner viennent d'être vérifiés, mais une erreur ne doit pas passer inaperçue
+    match app.prestige() {
+        Ok(()) => app.log.push(tf(
+            Msg::AutoPrestiged,
+            app.lang,
+            &[&format_lines(reached, app.config.decimal_places)],
+        )),
+        Err(error) => app.report(Err(error)),
+    }
+}
+
+/// Part of the gap between the shown and the real code lines closed at each frame.
+const SMOOTHING: f64 = 0.3;
+
+/// One frame of the counter rolling from `shown` toward `target`, see `SMOOTHING`.
+///
+/// Reaches `target` exactly once less than a line away, so the counter stops.
+fn approach(shown: Lines, target: Lines) -> Lines {
+    let gap = target.0 - shown.0;
+    if gap.abs() < 1. || !gap.is_finite() {
+        target
+    } else {
+        Lines(shown.0 + gap * SMOOTHING)
+    }
+}
+
+/// Whether `size` has room for the game, at least `min_width` by `min_height`.
+fn fits(size: Rect, min_width: u16, min_height: u16) -> bool {
+    size.width >= min_width && size.height >= min_height
+}
+
+/// Ask to resize the terminal until it `fits`, false if the player quit instead.
+fn wait_for_size<B: Backend>(
+    terminal: &mut Terminal<B>,
+    input: &mut dyn Input,
+    app: &App,
+) -> io::Result<bool> {
+    let (min_width, min_height) = (app.config.min_width, app.config.min_height);
+    loop {
+        let size = terminal.size()?;
+        if fits(size, min_width, min_height) {
+            return Ok(true);
+        }
+        terminal.draw(|f| {
+            let message = tf(
+                Msg::TerminalTooSmall,
+                app.lang,
+                &[&min_width, &min_height, &size.width, &size.height],
+            );
+            f.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), f.size());
+        })?;
+        // on vérifie à nouveau régulièrement, tous les terminaux ne signalent pas
+        // un redimensionnement
+        if !input.poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Some(key) = input.read()? {
+            if is_interrupt(&key) || key.code == KeyCode::Char(app.keys.quit) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Draw the session summary and wait for a key press before quitting.
+///
+/// Only after the quit key or command: quitting when idle doesn't wait, nobody is
+/// there to read it, and Ctrl-C is meant to leave at once.
+fn show_summary<B: Backend>(
+    terminal: &mut Terminal<B>,
+    input: &mut dyn Input,
+    app: &App,
+) -> io::Result<()> {
+    terminal.draw(|f| summary_ui(f, app))?;
+    // un redimensionnement ne compte pas comme une touche
+    while input.read()?.is_none() {
+        terminal.draw(|f| summary_ui(f, app))?;
+    }
+    Ok(())
+}
+
+/// Ring the terminal bell on `out`.
+///
+/// The bell character moves nothing on screen, so it's safe to write between two
+/// frames, right after one was drawn and flushed.
+fn ring_bell(out: &mut impl io::Write) -> io::Result<()> {
+    out.write_all(b"\x07")?;
+    out.flush()
+}
+
+/// Do what the input asked for, false once the game must stop.
+fn play(app: &mut App, state: GameState) -> bool {
+    let result = match state {
+        GameState::BuyItem(item_string) => {
+            // On veut pouvoir afficher l'erreur et sans paniquer
+            // en effet, on ne sait si ce que le joueur a entré est valide ou non
+            buy_item(app, item_string)
+        }
+        GameState::SellItem(item_string) => sell_item(app, item_string),
+        GameState::Trade(command) => trade(app, command),
+        GameState::Undo => undo_purchase(app),
+        GameState::Reset => {
+            app.reset();
+            return true;
+        }
+        GameState::Prestige => app.prestige(),
+        GameState::BuyUpgrade(name) => buy_upgrade(app, &name),
+        GameState::ToggleFavorite(name) => toggle_favorite(app, &name),
+        GameState::BuyFavorites => buy_favorites(app),
+        GameState::Compare(name) => compare(app, &name),
+        GameState::Import(code) => import_save(app, &code),
+        GameState::Unlock(item) => unlock(app, &item),
+        GameState::ConfirmBuy { item, count } => buy(app, &item, count),
+        GameState::ConfirmSell { item, count } => sell(app, &item, count),
+        GameState::UpgradeClick => upgrade_click(app),
+        GameState::SellWorst => sell_worst(app),
+        GameState::Macro(actions) => match run_macro(app, actions) {
+            Ok(true) => Ok(()),
+            Ok(false) => return false,
+            Err(error) => Err(error),
+        },
+        GameState::Noop => return true,
+        GameState::Quit | GameState::Interrupt => return false,
+    };
+    // un achat ou une vente change le conseil, sans attendre la prochaine seconde
+    app.advice = double_cps_advice(app);
+    app.track_outlook();
+    app.report(result);
+    true
+}
+
+/// Play a recorded game again without a terminal, giving the state it ends in.
+///
+/// The items are the recorded ones, only older recordings without them read `items_path`.
+///
+/// ## Errors
+///
+/// The errors of `App::new` if the items can't be loaded.
+fn replay(recording: &Recording, items_path: &str) -> Result<Save, ClidleError> {
+    let config = recording.config.clone();
+    let mut app = match &recording.catalog {
+        Some(catalog) => App::with_catalog(config, catalog, items_path)?,
+        None => App::new(config, items_path)?,
+    };
+    // le temps avance avec les ticks enregistrés, sans attendre
+    let clock = Rc::new(ManualClock::new());
+    app.set_clock(clock.clone());
+    app.restore(recording.start.clone());
+    for event in &recording.events {
+        match (event, event.key_event()) {
+            (RecordedEvent::Tick, _) => {
+                clock.advance(Duration::from_secs(1));
+                app.update(1);
+                auto_prestige(&mut app);
+            }
+            (RecordedEvent::CatchUp { secs }, _) => {
+                clock.advance(Duration::from_secs(*secs));
+                app.update(*secs);
+                auto_prestige(&mut app);
+            }
+            (_, Some(key)) => {
+                let state = handle_key(&mut app, key);
+                if !play(&mut app, state) {
+                    break;
+                }
+            }
+            // une touche inconnue, enregistrée par une autre version du jeu
+            (_, None) => {}
+        }
+    }
+    Ok(app.snapshot())
+}
+
+/// A production per second, converted to the `rate_unit` of the config and formatted.
+fn format_rate(app: &App, cps: f64) -> String {
+    format_lines(
+        cps * app.config.rate_unit.factor(),
+        app.config.decimal_places,
+    )
+}
+
+/// What productions are counted per, in words, following `rate_unit`.
+fn rate_label(app: &App) -> &'static str {
+    let msg = match app.config.rate_unit {
+        RateUnit::Second => Msg::PerSecond,
+        RateUnit::Minute => Msg::PerMinute,
+        RateUnit::Hour => Msg::PerHour,
+    };
+    t(msg, app.lang)
+}
+
+/// The help line with only the code lines count and the keys, in bold, for narrow terminals.
+fn short_help(help: Vec<Span>) -> Vec<Span> {
+    let mut help = help.into_iter();
+    let Some(owning) = help.next() else {
+        return Vec::new();
+    };
+    let mut short = vec![owning];
+    for key in help.filter(|span| span.style.add_modifier.contains(Modifier::BOLD)) {
+        short.push(key);
+        short.push(Span::raw(" "));
+    }
+    short
+}
+
+/// Frames of the spinner shown while items are producing.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// The spinner frame to draw, none when nothing is being produced.
+fn spinner(app: &App) -> Option<char> {
+    if app.paused || app.total_cps() <= 0. {
+        return None;
+    }
+    Some(SPINNER[(app.frame % SPINNER.len() as u64) as usize])
+}
+
+/// The item typed while buying or selling, whose owned count is charted.
+fn inspected_item(app: &App) -> Option<ItemId> {
+    let typed = match &app.input_mode {
+        InputMode::Buy | InputMode::Sell => parse_trade(Verb::Buy, &app.input).ok()?.item,
+        InputMode::Spinner { item } => item.clone(),
+        _ => return None,
+    };
+    app.find_item(&typed).map(|item| item.id)
+}
+
+/// The error of the last action for the messages, counting its repeats, none if it went fine.
+fn error_line(app: &App) -> Option<String> {
+    let error = app.error.as_ref().err()?.localized(app.lang);
+    Some(if app.error_repeats > 1 {
+        tf(
+            Msg::RepeatedErrorLine,
+            app.lang,
+            &[&error, &app.error_repeats],
+        )
+    } else {
+        tf(Msg::ErrorLine, app.lang, &[&error])
+    })
+}
+
+/// Where each part of the main screen is drawn.
+struct Areas {
+    help: Rect,
+    input: Rect,
+    owned: Rect,
+    messages: Rect,
+    status: Rect,
+    /// the big counter of focus mode, empty otherwise
+    counter: Rect,
+}
+
+/// Split the screen for `layout`.
+///
+/// The columns put the owned items on the left and the messages on the right,
+/// for wide terminals.
+fn areas(size: Rect, layout: UiLayout) -> Areas {
+    let middle = match layout {
+        UiLayout::Vertical => vec![Constraint::Percentage(50), Constraint::Percentage(50)],
+        UiLayout::Columns => vec![Constraint::Min(0)],
+    };
+    let constraints: Vec<Constraint> = [Constraint::Length(1), Constraint::Length(3)]
+        .into_iter()
+        .chain(middle)
+        .chain([Constraint::Length(1)])
+        .collect();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(constraints)
+        .split(size);
+    let (owned, messages) = match layout {
+        UiLayout::Vertical => (chunks[2], chunks[3]),
+        UiLayout::Columns => {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(chunks[2]);
+            (columns[0], columns[1])
+        }
+    };
+    Areas {
+        help: chunks[0],
+        input: chunks[1],
+        owned,
+        messages,
+        status: chunks[chunks.len() - 1],
+        counter: Rect::default(),
+    }
+}
+
+/// Split the screen for focus mode: only the help, the input and the counter,
+/// every other part is left empty.
+fn focus_areas(size: Rect) -> Areas {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(size);
+    Areas {
+        help: chunks[0],
+        input: chunks[1],
+        owned: Rect::default(),
+        messages: Rect::default(),
+        status: Rect::default(),
+        counter: chunks[2],
+    }
+}
+
+/// The help line for the current mode, with the style it's drawn in.
+fn help_line(app: &App) -> (Vec<Span<'_>>, Style) {
+    let lang = app.lang;
+    let places = app.config.decimal_places;
+    let key = |k| Span::styled(k, Style::default().add_modifier(Modifier::BOLD));
+    let bound =
+        |k: char| Span::styled(k.to_string(), Style::default().add_modifier(Modifier::BOLD));
+    let raw = |msg| Span::raw(t(msg, lang));
+    let mut owning = tf(
+        Msg::Owning,
+        lang,
+        &[&format_lines(app.shown_lines.0, places)],
+    );
+    if let Some(frame) = spinner(app) {
+        owning.insert_str(0, &format!("{frame} "));
+    }
+    let owning = Span::raw(owning);
+    let (mut msg, style) = match &app.input_mode {
+        InputMode::Normal if app.focus_mode => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                bound(app.keys.quit),
+                raw(Msg::ToExit),
+                bound(app.keys.code),
+                raw(Msg::ToCode),
+                bound(app.keys.buy),
+                raw(Msg::ToStartBuying),
+                bound(app.keys.focus),
+                raw(Msg::ToLeaveFocus),
+            ],
+            Style::default(),
+        ),
+        InputMode::Normal if app.paused => (
+            vec![
+                owning,
+                raw(Msg::Paused),
+                bound(app.keys.pause),
+                raw(Msg::ToResume),
+            ],
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
+        ),
+        InputMode::Normal => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                bound(app.keys.quit),
+                raw(Msg::ToExit),
+                bound(app.keys.code),
+                raw(Msg::ToCode),
+                bound(app.keys.upgrade_click),
+                raw(Msg::ToUpgradeClick),
+                bound(app.keys.auto_code),
+                raw(if app.auto_code {
+                    Msg::ToStopAutoCode
+                } else {
+                    Msg::ToAutoCode
+                }),
+                bound(app.keys.pause),
+                raw(Msg::ToPause),
+                bound(app.keys.buy),
+                raw(Msg::ToStartBuying),
+                bound(app.keys.sell),
+                raw(Msg::ToStartSelling),
+                bound(app.keys.sell_worst),
+                raw(Msg::ToSellWorst),
+                bound(app.keys.search),
+                raw(Msg::ToSearch),
+                bound(app.keys.undo),
+                raw(Msg::ToUndo),
+                bound(app.keys.reset),
+                raw(Msg::ToStartOver),
+                bound(app.keys.prestige),
+                raw(Msg::ToPrestige),
+                bound(app.keys.shop),
+                raw(Msg::ToOpenShop),
+                bound(app.keys.favorite),
+                raw(Msg::ToFavorite),
+                bound(app.keys.favorites_only),
+                raw(if app.favorites_only {
+                    Msg::ToShowAll
+                } else {
+                    Msg::ToShowFavorites
+                }),
+                bound(app.keys.buy_favorites),
+                raw(Msg::ToBuyFavorites),
+                bound(app.keys.compare),
+                raw(Msg::ToCompare),
+                bound(app.keys.rebuy),
+                raw(Msg::ToRebuy),
+                key("+"),
+                Span::raw("/"),
+                key("-"),
+                raw(Msg::ToChangeForecast),
+                bound(app.keys.layout),
+                raw(Msg::ToSwitchLayout),
+                bound(app.keys.import),
+                raw(Msg::ToStartImport),
+                bound(app.keys.command),
+                raw(Msg::ToStartCommand),
+                bound(app.keys.focus),
+                raw(Msg::ToFocus),
+                bound(app.keys.language),
+                raw(Msg::ToSwitchLanguage),
+            ],
+            Style::default().add_modifier(Modifier::RAPID_BLINK),
+        ),
+        InputMode::Buy => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopBuying),
+                key("Tab"),
+                raw(Msg::ToPickQuantity),
+                key("Enter"),
+                raw(Msg::ToBuy),
+            ],
+            Style::default(),
+        ),
+        InputMode::Spinner { item } => (
+            vec![
+                Span::raw(tf(Msg::SpinnerHelp, lang, &[&app.buy_quantity, item])),
+                raw(Msg::Press),
+                key("+"),
+                Span::raw("/"),
+                key("-"),
+                raw(Msg::ToChangeQuantity),
+                key("Esc"),
+                raw(Msg::ToTypeAgain),
+                key("Enter"),
+                raw(Msg::ToBuy),
+            ],
+            Style::default().fg(Color::Green),
+        ),
+        InputMode::Sell => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopSelling),
+                key("Enter"),
+                raw(Msg::ToSell),
+            ],
+            Style::default(),
+        ),
+        InputMode::Import => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopImporting),
+                key("Enter"),
+                raw(Msg::ToImport),
+            ],
+            Style::default(),
+        ),
+        InputMode::Command => (
+            vec![
+                owning,
+                raw(Msg::CommandHelp),
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopCommands),
+                key("Enter"),
+                raw(Msg::ToRunCommand),
+            ],
+            Style::default(),
+        ),
+        InputMode::ConfirmReset => (
+            vec![owning, raw(Msg::Press), key("y"), raw(Msg::ToConfirmReset)],
+            Style::default().fg(Color::Red),
+        ),
+        InputMode::ConfirmPrestige => (
+            vec![
+                Span::raw(tf(
+                    Msg::ConfirmPrestige,
+                    lang,
+                    &[&prestige_commits(
+                        app.lifetime_lines,
+                        app.config.prestige_lines,
+                    )],
+                )),
+                raw(Msg::Press),
+                key("y"),
+                raw(Msg::ToConfirmPrestige),
+            ],
+            Style::default().fg(Color::Yellow),
+        ),
+        InputMode::Shop => (
+            vec![
+                Span::raw(tf(Msg::ShopHelp, lang, &[&app.commits])),
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopShopping),
+                key("Enter"),
+                raw(Msg::ToBuyUpgrade),
+            ],
+            Style::default(),
+        ),
+        InputMode::Favorite => (
+            vec![
+                raw(Msg::FavoriteHelp),
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopFavoriting),
+                key("Enter"),
+                raw(Msg::ToToggleFavorite),
+            ],
+            Style::default(),
+        ),
+        InputMode::Compare => (
+            vec![
+                raw(Msg::CompareHelp),
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopComparing),
+                key("Enter"),
+                raw(Msg::ToAddToComparison),
+            ],
+            Style::default(),
+        ),
+        InputMode::ConfirmBuy {
+            item, count, price, ..
+        } => (
+            vec![
+                Span::raw(tf(
+                    Msg::ConfirmBuy,
+                    lang,
+                    &[&format_count(*count), item, price],
+                )),
+                raw(Msg::Press),
+                key("y"),
+                raw(Msg::ToConfirmBuy),
+            ],
+            Style::default().fg(Color::Yellow),
+        ),
+        InputMode::ConfirmUnlock { item, cost, .. } => (
+            vec![
+                Span::raw(tf(Msg::ConfirmUnlock, lang, &[item, cost])),
+                raw(Msg::Press),
+                key("y"),
+                raw(Msg::ToConfirmUnlock),
+            ],
+            Style::default().fg(Color::Yellow),
+        ),
+        InputMode::ConfirmSell { item, count, .. } => (
+            vec![
+                Span::raw(tf(Msg::ConfirmSell, lang, &[&format_count(*count), item])),
+                raw(Msg::Press),
+                key("y"),
+                raw(Msg::ToConfirmSell),
+            ],
+            Style::default().fg(Color::Red),
+        ),
+        InputMode::Search => (
+            vec![
+                owning,
+                raw(Msg::Press),
+                key("Esc"),
+                raw(Msg::ToStopSearching),
+                key("Enter"),
+                raw(Msg::ToBuyFirstMatch),
+            ],
+            Style::default(),
+        ),
+    };
+    // seule l'aide du mode normal est longue, les autres modes gardent leurs consignes
+    if let InputMode::Normal = app.input_mode {
+        match app.config.help_verbosity {
+            HelpVerbosity::Full => {}
+            HelpVerbosity::Short => msg = short_help(msg),
+            HelpVerbosity::Hidden => {
+                // il n'y a pas d'autre aide, il faut au moins savoir comment quitter
+                msg.truncate(1);
+                msg.extend([raw(Msg::Press), bound(app.keys.quit), raw(Msg::ToExit)]);
+            }
+        }
+    }
+    if app.config.hardcore {
+        msg.insert(
+            0,
+            Span::styled(
+                t(Msg::HardcoreTag, lang),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+        );
+    }
+    (msg, style)
+}
+
+// Permet de gérer tout l'affichage
+fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let areas = if app.focus_mode {
+        focus_areas(f.size())
+    } else {
+        areas(f.size(), app.layout)
+    };
+
+    // seul l'affichage glisse, les lignes elles-mêmes restent exactes
+    app.shown_lines = if app.config.smooth_counter {
+        approach(app.shown_lines, app.code_lines)
+    } else {
+        app.code_lines
+    };
+
+    let lang = app.lang;
+    let places = app.config.decimal_places;
+    let (msg, style) = help_line(app);
+    let mut text = Text::from(Spans::from(msg));
+    text.patch_style(style);
+    if app.highlight_until.is_some() {
+        text.patch_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+    }
+    match app.flash {
+        Some((Flash::Gain, _)) => {
+            text.patch_style(Style::default().fg(Color::Black).bg(Color::Green))
+        }
+        Some((Flash::Loss, _)) => {
+            text.patch_style(Style::default().fg(Color::Black).bg(Color::Red))
+        }
+        None => {}
+    }
+    let help_message = Paragraph::new(text);
+    f.render_widget(help_message, areas.help);
+
+    // les bordures prennent une colonne de chaque côté
+    let input_text = visible_tail(&app.input, areas.input.width.saturating_sub(2) as usize);
+    let input = Paragraph::new(input_text)
+        .style(match app.input_mode {
+            InputMode::Normal
+            | InputMode::Search
+            | InputMode::ConfirmReset
+            | InputMode::ConfirmPrestige
+            | InputMode::ConfirmBuy { .. }
+            | InputMode::ConfirmSell { .. }
+            | InputMode::ConfirmUnlock { .. } => Style::default(),
+            InputMode::Buy | InputMode::Spinner { .. } => Style::default().fg(Color::Green),
+            InputMode::Import => Style::default().fg(Color::Cyan),
+            InputMode::Command => Style::default().fg(Color::Magenta),
+            InputMode::Shop | InputMode::Favorite | InputMode::Compare => {
+                Style::default().fg(Color::Yellow)
+            }
+            InputMode::Sell => Style::default().fg(Color::Red),
+        })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t(Msg::InputTitle, lang)),
+        );
+    f.render_widget(input, areas.input);
+    match app.input_mode {
+        InputMode::Normal
+        | InputMode::Search
+        | InputMode::ConfirmReset
+        | InputMode::ConfirmPrestige
+        | InputMode::ConfirmBuy { .. }
+        | InputMode::ConfirmSell { .. }
+        | InputMode::ConfirmUnlock { .. }
+        | InputMode::Spinner { .. } =>
+            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
+            {}
+
+        InputMode::Sell
+        | InputMode::Buy
+        | InputMode::Import
+        | InputMode::Command
+        | InputMode::Shop
+        | InputMode::Favorite
+        | InputMode::Compare => {
+            // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
+            f.set_cursor(
+                // Put cursor past the end of the visible input text
+                areas.input.x + input_text.width() as u16 + 1,
+                // Move one line down, from the border to the input line
+                areas.input.y + 1,
+            )
+        }
+    }
+
+    let total_cps = app.total_cps();
+    let owned: Vec<ListItem> = app
+        .owned_items
+        .iter()
+        .map(|(item_id, item_count)| {
+            let item_type = app.item(*item_id);
+            let item_cps = item_count * app.unit_cps(*item_id);
+
+            let content = vec![Spans::from(Span::raw(tf(
+                Msg::OwnedLine,
+                lang,
+                &[
+                    &format_count(*item_count),
+                    &item_type.long_name,
+                    &format_rate(app, item_cps),
+                    &rate_label(app),
+                ],
+            )))];
+            let theme = &app.config.theme;
+            let style = match contribution(item_cps, total_cps) {
+                Some(Contribution::Major) => Style::default().fg(theme.major),
+                Some(Contribution::Minor) => Style::default().fg(theme.minor),
+                Some(Contribution::Negligible) => Style::default().fg(theme.negligible),
+                None => Style::default(),
+            };
+            ListItem::new(content).style(style)
+        })
+        .collect();
+    let owned = List::new(owned).block(
+        Block::default().borders(Borders::ALL).title(tf(
+            Msg::OwnedTitle,
+            lang,
+            &[
+                &app.lifetime_purchases,
+                &format_rate(app, app.peak_cps),
+                &rate_label(app),
+                &average_rate(app.lifetime_lines, app.played_secs)
+                    .map_or_else(|| "-".to_string(), |rate| format_rate(app, rate)),
+                &rate_label(app),
+            ],
+        )),
+    );
+    let owned_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(areas.owned);
+    f.render_widget(owned, owned_chunks[0]);
+
+    let preview = preview_buy(app);
+    let inspected = inspected_item(app);
+    let description = inspected
+        .map(|item_id| {
+            wrap_text(
+                &app.item(item_id).description,
+                owned_chunks[1].width.saturating_sub(2).into(),
+            )
+        })
+        .unwrap_or_default();
+    let side_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(5),
+                Constraint::Length(if preview.is_some() { 5 } else { 0 }),
+                Constraint::Length(if inspected.is_some() { 4 } else { 0 }),
+                Constraint::Length(if description.is_empty() {
+                    0
+                } else {
+                    description.len() as u16 + 2
+                }),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(owned_chunks[1]);
+    let top: Vec<ListItem> = app
+        .production_ranking()
+        .into_iter()
+        .take(3)
+        .enumerate()
+        .map(|(rank, (item_id, cps))| {
+            ListItem::new(Spans::from(Span::raw(format!(
+                "{}. {} {}{}",
+                rank + 1,
+                app.item(item_id).name,
+                format_rate(app, cps),
+                app.config.rate_unit.suffix()
+            ))))
+        })
+        .collect();
+    let top = List::new(top).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t(Msg::TopTitle, lang)),
+    );
+    f.render_widget(top, side_chunks[0]);
+
+    if let Some(preview) = preview {
+        let next_in = match preview.next_in {
+            Some(secs) => tf(Msg::PreviewNextIn, lang, &[&format!("{secs:.0}")]),
+            None => t(Msg::PreviewNeverNext, lang).to_string(),
+        };
+        let lines = [
+            tf(
+                Msg::PreviewLines,
+                lang,
+                &[
+                    &format_lines(preview.lines.0 .0, places),
+                    &format_lines(preview.lines.1 .0, places),
+                ],
+            ),
+            tf(
+                Msg::PreviewCps,
+                lang,
+                &[
+                    &format_rate(app, preview.cps.0),
+                    &format_rate(app, preview.cps.1),
+                    &rate_label(app),
+                ],
+            ),
+            next_in,
+        ];
+        let lines: Vec<ListItem> = lines
+            .into_iter()
+            .map(|line| ListItem::new(Spans::from(Span::raw(line))))
+            .collect();
+        let mut title = tf(
+            Msg::PreviewTitle,
+            lang,
+            &[&format_count(preview.count), &preview.name],
+        );
+        if let Some(percent) = preview.discount {
+            title += &tf(Msg::PreviewDiscount, lang, &[&percent]);
+        }
+        let preview = List::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(preview, side_chunks[1]);
+    }
+
+    if let Some(item_id) = inspected {
+        let data: Vec<u64> = app.owned_samples[item_id.0]
+            .values()
+            .iter()
+            .map(|owned| owned.round() as u64)
+            .collect();
+        let item = app.item(item_id);
+        let title = tf(Msg::OwnedHistoryTitle, lang, &[&item.name]) + &break_even_label(app, item);
+        let chart = Sparkline::default()
+            .data(&data)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(chart, side_chunks[2]);
+    }
+
+    if !description.is_empty() {
+        let lines: Vec<ListItem> = description
+            .into_iter()
+            .map(|line| ListItem::new(Spans::from(Span::raw(line))))
+            .collect();
+        let description = List::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t(Msg::DescriptionTitle, lang)),
+        );
+        f.render_widget(description, side_chunks[3]);
+    }
+
+    let recent: Vec<ListItem> = app
+        .history
+        .recent()
+        .map(|purchase| {
+            ListItem::new(Spans::from(Span::raw(tf(
+                Msg::RecentLine,
+                lang,
+                &[
+                    &format_count(purchase.count),
+                    &app.item(purchase.item_id).name,
+                    &purchase.price,
+                    &app.clock
+                        .now()
+                        .saturating_duration_since(purchase.at)
+                        .as_secs(),
+                ],
+            ))))
+        })
+        .collect();
+    let recent = List::new(recent).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t(Msg::RecentTitle, lang)),
+    );
+    f.render_widget(recent, side_chunks[4]);
+
+    let page_size = app.config.page_size;
+    let page_count = page_count(app.listed_items().len(), page_size);
+    app.page = app.page.min(page_count - 1);
+    let best = best_purchase(app);
+    let icon_width = app
+        .items_index
+        .iter()
+        .filter_map(|item| item.icon.as_deref())
+        .map(UnicodeWidthStr::width)
+        .max();
+    let listed = app.listed_items();
+    let mut messages: Vec<ListItem> = listed[page_range(listed.len(), page_size, app.page)]
+        .iter()
+        .map(|item| {
+            // TODO: on peut afficher le prix de chaque item
+            let price = app.price_of(item);
+            let line = item_row(app, item, best, icon_width);
+            let is_best = best == Some(item.id);
+            let content = vec![Spans::from(Span::raw(line))];
+            let theme = &app.config.theme;
+            let color = if app.is_maxed(item) {
+                theme.maxed
+            } else if app.can_afford(price) {
+                theme.affordable
+            } else {
+                theme.unaffordable
+            };
+            let style = Style::default().fg(color);
+            ListItem::new(content).style(if is_best {
+                style.add_modifier(Modifier::BOLD)
+            } else {
+                style
+            })
+        })
+        .collect();
+
+    // la boutique prend la place des items
+    if let InputMode::Shop = app.input_mode {
+        messages = app
+            .config
+            .prestige_upgrades
+            .iter()
+            .map(|upgrade| {
+                let mut line = tf(
+                    Msg::UpgradeLine,
+                    lang,
+                    &[&upgrade.name, &upgrade.cost, &upgrade.cps_multiplier],
+                );
+                let bought = app.prestige_upgrades.contains(&upgrade.name);
+                if bought {
+                    line += t(Msg::UpgradeBought, lang);
+                }
+                let color = if bought {
+                    app.config.theme.maxed
+                } else if app.commits >= upgrade.cost {
+                    app.config.theme.affordable
+                } else {
+                    app.config.theme.unaffordable
+                };
+                ListItem::new(Spans::from(Span::raw(line))).style(Style::default().fg(color))
+            })
+            .collect();
+    }
+
+    // les entrées les plus récentes d'abord
+    messages.extend(
+        app.log
+            .iter()
+            .rev()
+            .take(5)
+            .map(|entry| ListItem::new(Spans::from(Span::raw(entry.as_str())))),
+    );
+
+    // croître ou stagner est dans le journal au changement, perdre des lignes reste affiché
+    let cps = app.total_cps();
+    if let Outlook::Doomed(secs) = outlook(app.code_lines, cps) {
+        messages.push(
+            ListItem::new(Spans::from(Span::raw(tf(
+                Msg::LosingLines,
+                lang,
+                &[
+                    &format_rate(app, -cps),
+                    &rate_label(app),
+                    &format!("{secs:.0}"),
+                ],
+            ))))
+            .style(Style::default().fg(Color::Red)),
+        );
+    }
+
+    if let Some(rate) = app.gains.rate(app.clock.now()) {
+        messages.push(ListItem::new(Spans::from(Span::raw(tf(
+            Msg::MeasuredRate,
+            lang,
+            &[&format_rate(app, rate), &rate_label(app)],
+        )))));
+    }
+
+    if let Some(advice) = &app.advice {
+        let purchases: Vec<String> = advice
+            .purchases
+            .iter()
+            .map(|(item_id, count)| format!("{count} {}", app.item(*item_id).name))
+            .collect();
+        messages.push(ListItem::new(Spans::from(Span::raw(tf(
+            Msg::DoubleCpsAdvice,
+            lang,
+            &[&purchases.join(", "), &advice.cost],
+        )))));
+    }
+
+    if let Some(line) = error_line(app) {
+        messages.push(ListItem::new(Spans::from(Span::raw(line))))
+    }
+
+    let messages = List::new(messages).block(Block::default().borders(Borders::ALL).title(tf(
+        Msg::MessagesTitle,
+        lang,
+        &[&(app.page + 1), &page_count],
+    )));
+    f.render_widget(messages, areas.messages);
+
+    let status = Paragraph::new(status_line(app, areas.status.width as usize))
+        .style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_widget(status, areas.status);
+
+    if app.focus_mode {
+        let counter = Paragraph::new(vec![
+            Spans::from(Span::styled(
+                tf(
+                    Msg::FocusLines,
+                    lang,
+                    &[&format_large(app.shown_lines.0, places)],
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Spans::from(Span::raw(tf(
+                Msg::FocusCps,
+                lang,
+                &[&format_rate(app, app.total_cps()), &rate_label(app)],
+            ))),
+            // sans les messages, l'erreur de la dernière action s'affiche ici
+            Spans::from(Span::raw(error_line(app).unwrap_or_default())),
+        ])
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(counter, areas.counter);
+    }
+
+    if let InputMode::Search = app.input_mode {
+        search_overlay(f, app);
+    }
+
+    if let InputMode::Compare = app.input_mode {
+        compare_overlay(f, app);
+    }
+
+    if let Some(debug) = app.debug.as_ref().filter(|debug| debug.visible) {
+        debug.render(f);
+    }
+}
+
+/// The always visible vitals: lines, production, owned items and input mode.
+///
+/// Falls back to a terse version when the full one doesn't fit in `width`.
+fn status_line(app: &App, width: usize) -> String {
+    let lang = app.lang;
+    let mode = t(
+        match app.input_mode {
+            InputMode::Normal => Msg::ModeNormal,
+            InputMode::Buy => Msg::ModeBuy,
+            InputMode::Spinner { .. } => Msg::ModeSpinner,
+            InputMode::Sell => Msg::ModeSell,
+            InputMode::Search => Msg::ModeSearch,
+            InputMode::ConfirmReset => Msg::ModeConfirmReset,
+            InputMode::ConfirmPrestige => Msg::ModeConfirmPrestige,
+            InputMode::Shop => Msg::ModeShop,
+            InputMode::Favorite => Msg::ModeFavorite,
+            InputMode::Compare => Msg::ModeCompare,
+            InputMode::ConfirmBuy { .. } => Msg::ModeConfirmBuy,
+            InputMode::ConfirmUnlock { .. } => Msg::ModeConfirmUnlock,
+            InputMode::ConfirmSell { .. } => Msg::ModeConfirmSell,
+            InputMode::Import => Msg::ModeImport,
+            InputMode::Command => Msg::ModeCommand,
+        },
+        lang,
+    );
+    let places = app.config.decimal_places;
+    let lines = format_lines(app.shown_lines.0, places);
+    let cps = format_rate(app, app.total_cps());
+    let owned = format_count(app.owned_items.values().sum());
+    let forecast = format_large(
+        forecast(app.code_lines, app.total_cps(), app.forecast_secs).0,
+        places,
+    );
+    let next_tier = match app.config.click_tiers.get(app.click_tier) {
+        Some(tier) => tf(Msg::NextClickTier, lang, &[&tier.cost]),
+        None => t(Msg::LastClickTier, lang).to_string(),
+    };
+    let click = tf(
+        Msg::ClickTier,
+        lang,
+        &[&app.click_tier, &app.click_power(), &next_tier],
+    );
+    let full = tf(
+        Msg::StatusLine,
+        lang,
+        &[
+            &lines,
+            &cps,
+            &rate_label(app),
+            &owned,
+            &click,
+            &app.forecast_secs,
+            &forecast,
+            &mode,
+        ],
+    );
+    if full.width() <= width {
+        full
+    } else {
+        let suffix = app.config.rate_unit.suffix();
+        format!("{lines} | {cps}{suffix} | {owned} | {mode}")
+    }
+}
+
+/// The end of `input` that fits in `width` columns with the cursor after it.
+///
+/// When the input is too long it scrolls horizontally, so what is being typed
+/// stays visible. Wide characters count for their real display width.
+fn visible_tail(input: &str, width: usize) -> &str {
+    // une colonne est gardée pour le curseur
+    let mut available = width.saturating_sub(1);
+    let mut start = input.len();
+    for (i, c) in input.char_indices().rev() {
+        let w = c.width().unwrap_or(0);
+        if w > available {
+            break;
+        }
+        available -= w;
+        start = i;
+    }
+    &input[start..]
+}
+
+/// `text` cut into lines of at most `width` columns, breaking between words.
+///
+/// A word wider than a whole line is cut wherever it overflows.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let needed = if line.is_empty() { 0 } else { 1 } + word.width();
+        if !line.is_empty() && line.width() + needed > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        for c in word.chars() {
+            if line.width() + c.width().unwrap_or(0) > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws the search popup over the rest of the ui.
+fn search_overlay<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let area = centered_rect(60, 50, f.size());
+    // efface ce qui a été dessiné en dessous
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let query_text = visible_tail(
+        &app.search_query,
+        chunks[0].width.saturating_sub(2) as usize,
+    );
+    let query = Paragraph::new(query_text)
+        .style(Style::default().fg(Color::Green))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t(Msg::SearchTitle, app.lang)),
+        );
+    f.render_widget(query, chunks[0]);
+    f.set_cursor(chunks[0].x + query_text.width() as u16 + 1, chunks[0].y + 1);
+
+    let matches: Vec<ListItem> = filter_items(&app.items_index, &app.search_query)
+        .into_iter()
+        .map(|item| {
+            ListItem::new(Spans::from(Span::raw(tf(
+                Msg::MatchLine,
+                app.lang,
+                &[&item.long_name, &item.name],
+            ))))
+        })
+        .collect();
+    let matches = List::new(matches).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t(Msg::MatchesTitle, app.lang)),
+    );
+    f.render_widget(matches, chunks[1]);
+}
+
+/// Draws what this session brought, see `show_summary`.
+fn summary_ui<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let lang = app.lang;
+    let stats = &app.stats;
+    let places = app.config.decimal_places;
+    let lines = vec![
+        Spans::from(tf(
+            Msg::SummaryLines,
+            lang,
+            &[&format_large(stats.lines_produced.0, places)],
+        )),
+        Spans::from(tf(Msg::SummaryPurchases, lang, &[&stats.purchases])),
+        Spans::from(tf(
+            Msg::SummaryThresholds,
+            lang,
+            &[&stats.thresholds_reached],
+        )),
+        Spans::from(tf(
+            Msg::SummaryTime,
+            lang,
+            &[&stats.duration(app.clock.now()).as_secs()],
+        )),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            t(Msg::SummaryAnyKey, lang),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ];
+    let area = centered_rect(60, 50, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t(Msg::SummaryTitle, lang)),
+        ),
+        area,
+    );
+}
+
+/// Draws the two compared items side by side over the rest of the ui.
+fn compare_overlay<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let area = centered_rect(60, 30, f.size());
+    // efface ce qui a été dessiné en dessous
+    f.render_widget(Clear, area);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+    let lang = app.lang;
+    for (slot, column) in app.compared.iter().zip(columns) {
+        let (title, lines) = match slot.map(|item_id| comparison(app, item_id)) {
+            Some(compared) => {
+                let efficiency = compared
+                    .efficiency
+                    .map_or_else(|| "-".to_string(), |rate| format_rate(app, rate));
+                let break_even = match compared.break_even {
+                    Some(secs) => tf(Msg::CompareBreakEven, lang, &[&format!("{secs:.0}")]),
+                    None => t(Msg::CompareNeverBreaksEven, lang).to_string(),
+                };
+                let lines = vec![
+                    tf(Msg::CompareCost, lang, &[&compared.price]),
+                    tf(
+                        Msg::CompareCps,
+                        lang,
+                        &[
+                            &format_rate(app, compared.cps),
+                            &app.config.rate_unit.suffix(),
+                        ],
+                    ),
+                    tf(
+                        Msg::CompareEfficiency,
+                        lang,
+                        &[&efficiency, &app.config.rate_unit.suffix()],
+                    ),
+                    break_even,
+                ];
+                (compared.name, lines)
+            }
+            None => (
+                t(Msg::CompareTitle, lang).to_string(),
+                vec![t(Msg::CompareEmpty, lang).to_string()],
+            ),
+        };
+        let lines: Vec<ListItem> = lines
+            .into_iter()
+            .map(|line| ListItem::new(Spans::from(Span::raw(line))))
+            .collect();
+        let list = List::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(list, column);
+    }
+}
+
+/// A rectangle centered in `r`, taking the given percentages of its width and height.
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Affordability, BulkDiscount};
+
+    /// The items of the tests, like the `items.json` shipped with the game.
+    const ITEMS: &str = r#"[
+        {"cps": 0.1, "cost": 5, "name": "dev", "long_name": "developer"},
+        {"cps": 2, "cost": 3000, "name": "f", "long_name": "feature"},
+        {"cps": 0.5, "cost": 10, "name": "git", "long_name": "version control system"}
+    ]"#;
+
+    /// A new game of `items` with `config`, always drawing the same noise.
+    fn game_of(items: &str, config: Config) -> App {
+        let config = Config {
+            seed: Some(1),
+            ..config
+        };
+        App::with_catalog(config, items, "items.json").unwrap()
+    }
+
+    /// A new game of `ITEMS` with `config`.
+    fn game(config: Config) -> App {
+        game_of(ITEMS, config)
+    }
+
+    #[test]
+    fn aliases_resolve_to_items() {
+        let mut config = Config::default();
+        config.aliases.insert("d".to_string(), "dev".to_string());
+        config
+            .aliases
+            .insert("nobody".to_string(), "ghost".to_string());
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..config
+        });
+        assert_eq!(app.find_item("d").map(|item| item.id), Some(ItemId(0)));
+        assert!(app.find_item("nobody").is_none());
+        buy_item(&mut app, "d 2".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(0)), 2.);
+        // le nom canonique, pas l'alias tapé
+        assert!(app.log.last().unwrap().contains("dev"));
+    }
+
+    #[test]
+    fn item_names_win_over_aliases() {
+        let mut config = Config::default();
+        config.aliases.insert("git".to_string(), "dev".to_string());
+        let app = game(config);
+        assert_eq!(app.find_item("git").map(|item| item.id), Some(ItemId(2)));
+    }
+
+    #[test]
+    fn session_counters_accumulate() {
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        });
+        buy_item(&mut app, "git 2".to_string()).unwrap();
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        app.update(10);
+        app.produce(Lines(app.click_power()));
+        // 2 git et 1 dev pendant 10 secondes, et une frappe
+        let produced = 10. * (2. * 0.5 + 0.1) + 1.;
+        assert_eq!(app.stats.purchases, 3);
+        assert!((app.stats.lines_produced.0 - produced).abs() < 1e-9);
+        assert!((app.stats.peak_cps - 1.1).abs() < 1e-9);
+        assert_eq!(app.stats.lines_produced, app.lifetime_lines);
+    }
+
+    #[test]
+    fn drains_are_not_production() {
+        let mut app = game(Config {
+            starting_lines: Lines(10.),
+            ..Config::default()
+        });
+        app.produce(Lines(5.));
+        app.produce(Lines(-8.));
+        app.produce(Lines(-100.));
+        assert_eq!(app.code_lines, Lines::ZERO);
+        assert_eq!(app.stats.lines_produced, Lines(5.));
+        assert_eq!(app.lifetime_lines, Lines(5.));
+    }
+
+    #[test]
+    fn maintenance_items_drain_lines() {
+        let items = r#"[
+            {"cps": 1, "cost": 5, "name": "dev", "long_name": "developer"},
+            {"cps": -3, "cost": 0, "name": "legacy", "long_name": "legacy code"}
+        ]"#;
+        let mut config = Config {
+            starting_lines: Lines(10.),
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 1);
+        config.starting_items.insert("legacy".to_string(), 1);
+        let mut app = game_of(items, config);
+        assert_eq!(app.total_cps(), -2.);
+        assert_eq!(
+            outlook(app.code_lines, app.total_cps()),
+            Outlook::Doomed(5.)
+        );
+        app.update(3);
+        assert_eq!(app.code_lines, Lines(4.));
+        // jamais en dessous de zéro
+        app.update(10);
+        assert_eq!(app.code_lines, Lines::ZERO);
+        // le dev seul produit de nouveau
+        sell(&mut app, "legacy", 1.).unwrap();
+        app.update(2);
+        assert_eq!(app.code_lines, Lines(2.));
+        assert_eq!(outlook(app.code_lines, app.total_cps()), Outlook::Growing);
+    }
+
+    /// A key press without modifiers.
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn filter_matches_names_and_long_names() {
+        let app = game(Config::default());
+        let names = |query| -> Vec<&str> {
+            filter_items(&app.items_index, query)
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect()
+        };
+        assert_eq!(names("GIT"), ["git"]);
+        assert_eq!(names("e"), ["dev", "f", "git"]);
+        assert_eq!(names("control"), ["git"]);
+        assert!(names("nothing").is_empty());
+    }
+
+    #[test]
+    fn search_needs_a_query_to_buy() {
+        let mut app = game(Config::default());
+        app.input_mode = InputMode::Search;
+        assert!(matches!(
+            handle_key(&mut app, press(KeyCode::Enter)),
+            GameState::Noop
+        ));
+        assert!(matches!(app.input_mode, InputMode::Search));
+        handle_key(&mut app, press(KeyCode::Char('v')));
+        match handle_key(&mut app, press(KeyCode::Enter)) {
+            GameState::BuyItem(item) => assert_eq!(item, "dev"),
+            _ => panic!("enter should buy the top match"),
+        }
+    }
+
+    #[test]
+    fn lifetime_purchases_only_climb() {
+        let mut app = game(Config {
+            starting_lines: Lines(1000.),
+            ..Config::default()
+        });
+        buy_item(&mut app, "dev 3".to_string()).unwrap();
+        assert_eq!(
+            (app.lifetime_purchases, app.owned_count(ItemId(0))),
+            (3, 3.)
+        );
+        sell_item(&mut app, "dev 2".to_string()).unwrap();
+        assert_eq!(
+            (app.lifetime_purchases, app.owned_count(ItemId(0))),
+            (3, 1.)
+        );
+        buy_item(&mut app, "git".to_string()).unwrap();
+        undo_purchase(&mut app).unwrap();
+        assert_eq!(
+            (app.lifetime_purchases, app.owned_count(ItemId(2))),
+            (4, 0.)
+        );
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        assert_eq!(
+            (app.lifetime_purchases, app.owned_count(ItemId(0))),
+            (5, 2.)
+        );
+    }
+
+    #[test]
+    fn config_seeds_a_new_game() {
+        let mut config = Config {
+            starting_lines: Lines(1234.5),
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 3);
+        config.starting_items.insert("git".to_string(), 1);
+        config.starting_items.insert("f".to_string(), 0);
+        let app = game(config);
+        assert_eq!(app.code_lines, Lines(1234.5));
+        assert_eq!(app.owned_items.len(), 2);
+        assert_eq!(app.owned_count(ItemId(0)), 3.);
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+    }
+
+    #[test]
+    fn starting_items_must_exist_and_fit() {
+        let items = r#"[{"cps": 1, "cost": 5, "name": "dev", "long_name": "dev", "max": 2}]"#;
+        for (name, count) in [("ghost", 1), ("dev", 3)] {
+            let mut config = Config::default();
+            config.starting_items.insert(name.to_string(), count);
+            assert!(matches!(
+                App::with_catalog(config, items, "items.json"),
+                Err(ClidleError::ConfigMalformed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejected_transactions_change_nothing() {
+        let items = r#"[
+            {"cps": 1, "cost": 10, "name": "dev", "long_name": "developer", "max": 4}
+        ]"#;
+        let mut config = Config {
+            starting_lines: Lines(15.),
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 2);
+        let mut app = game_of(items, config);
+        let state = |app: &App| (app.code_lines, app.owned_items.clone(), app.log.len());
+        let before = state(&app);
+        // trop chers pris ensemble, au-delà du max, plus qu'il n'en reste à vendre
+        assert!(matches!(
+            buy_item(&mut app, "dev 2".to_string()),
+            Err(ClidleError::NotEnoughLines(..))
+        ));
+        assert!(matches!(
+            buy_item(&mut app, "dev 3".to_string()),
+            Err(ClidleError::ItemMaxedOut(..))
+        ));
+        assert!(matches!(
+            sell_item(&mut app, "dev 3".to_string()),
+            Err(ClidleError::SellingItemNotOwned(..))
+        ));
+        assert_eq!(state(&app), before);
+        assert!(app.history.last().is_none());
+    }
+
+    #[test]
+    fn next_milestone_counts() {
+        assert_eq!(to_next_milestone(0., 25), 25.);
+        assert_eq!(to_next_milestone(1., 25), 24.);
+        assert_eq!(to_next_milestone(24., 25), 1.);
+        assert_eq!(to_next_milestone(25., 25), 25.);
+        assert_eq!(to_next_milestone(60., 25), 15.);
+        assert_eq!(to_next_milestone(2.5, 10), 7.5);
+        // un palier nul compte comme 1
+        assert_eq!(to_next_milestone(3., 0), 1.);
+    }
+
+    #[test]
+    fn next_buys_all_or_nothing() {
+        let mut config = Config {
+            starting_lines: Lines(100.),
+            milestone: 10,
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 3);
+        let mut app = game(config);
+        assert!(matches!(
+            buy_item(&mut app, "git next".to_string()),
+            Err(ClidleError::NotEnoughLines(_, 100, _))
+        ));
+        assert_eq!(app.owned_count(ItemId(2)), 0.);
+        buy_item(&mut app, "dev next".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(0)), 10.);
+        assert_eq!(app.code_lines, Lines(65.));
+    }
+
+    #[test]
+    fn ctrl_c_quits_from_any_mode() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(is_interrupt(&ctrl_c));
+        assert!(!is_interrupt(&press(KeyCode::Char('c'))));
+        let mut app = game(Config::default());
+        for mode in [InputMode::Normal, InputMode::Buy, InputMode::Command] {
+            app.input_mode = mode;
+            assert!(matches!(handle_key(&mut app, ctrl_c), GameState::Interrupt));
+            assert!(app.input.is_empty());
+        }
+    }
+
+    #[test]
+    fn break_even_math() {
+        assert_eq!(break_even_secs(100, 2.), Some(50.));
+        assert_eq!(break_even_secs(5, 0.1), Some(50.));
+        assert_eq!(break_even_secs(0, 0.), Some(0.));
+        assert_eq!(break_even_secs(100, 0.), None);
+        assert_eq!(break_even_secs(100, -1.), None);
+    }
+
+    #[test]
+    fn break_even_label_follows_the_item() {
+        let items = r#"[
+            {"cps": 2, "cost": 100, "name": "dev", "long_name": "developer"},
+            {"cps": 0, "cost": 10, "name": "idle", "long_name": "idle"},
+            {"cps": 1, "cost": 0, "name": "free", "long_name": "free"},
+            {"cps": 1, "cost": 10, "name": "vault", "long_name": "vault", "unlock_cost": 500}
+        ]"#;
+        let app = game_of(items, Config::default());
+        let label = |id| break_even_label(&app, app.item(ItemId(id)));
+        assert_eq!(label(0), ", pays for itself in 50s");
+        assert_eq!(label(1), ", never pays for itself");
+        assert_eq!(label(2), ", free");
+        assert_eq!(label(3), ", locked (500 lines to unlock)");
+    }
+
+    #[test]
+    fn page_slices_and_clamping() {
+        assert_eq!(page_count(0, 10), 1);
+        assert_eq!(page_count(10, 10), 1);
+        assert_eq!(page_count(11, 10), 2);
+        assert_eq!(page_count(5, 0), 5);
+        assert_eq!(page_range(25, 10, 0), 0..10);
+        assert_eq!(page_range(25, 10, 2), 20..25);
+        // au-delà de la dernière page, on reste sur la dernière
+        assert_eq!(page_range(25, 10, 7), 20..25);
+        assert_eq!(page_range(0, 10, 3), 0..0);
+    }
+
+    #[test]
+    fn paging_keys_stay_in_range() {
+        let mut app = game(Config {
+            page_size: 2,
+            ..Config::default()
+        });
+        handle_key(&mut app, press(KeyCode::PageUp));
+        assert_eq!(app.page, 0);
+        for _ in 0..3 {
+            handle_key(&mut app, press(KeyCode::PageDown));
+        }
+        assert_eq!(app.page, 1);
+        // les ids restent ceux du catalogue sur chaque page
+        let listed = app.listed_items();
+        let page = &listed[page_range(listed.len(), 2, app.page)];
+        assert_eq!(
+            page.iter().map(|item| item.id).collect::<Vec<_>>(),
+            [ItemId(2)]
+        );
+    }
+
+    #[test]
+    fn ranking_breaks_ties_by_id() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 1);
+        config.starting_items.insert("dev".to_string(), 5);
+        config.starting_items.insert("f".to_string(), 1);
+        let app = game(config);
+        let ranking: Vec<ItemId> = app
+            .production_ranking()
+            .into_iter()
+            .map(|(item_id, _)| item_id)
+            .collect();
+        // dev et git produisent autant, dev a le plus petit id
+        assert_eq!(ranking, [ItemId(1), ItemId(0), ItemId(2)]);
+        assert_eq!(worst_performer(&app), Some(ItemId(2)));
+    }
+
+    #[test]
+    fn catalog_is_read_from_stdin_with_a_dash() {
+        let stdin = ITEMS.as_bytes();
+        assert_eq!(read_catalog("-", stdin).unwrap(), ITEMS);
+        // un chemin de fichier ne touche pas à l'entrée standard
+        assert!(matches!(
+            read_catalog("does/not/exist.json", stdin),
+            Err(ClidleError::ItemsUnreadable(path, _)) if path == "does/not/exist.json"
+        ));
+        let invalid: &[u8] = &[0xff, 0xfe];
+        assert!(matches!(
+            read_catalog("-", invalid),
+            Err(ClidleError::ItemsUnreadable(path, _)) if path == "-"
+        ));
+    }
+
+    #[test]
+    fn forecast_projects_the_production() {
+        assert_eq!(forecast(Lines(10.), 2.5, 60), Lines(160.));
+        assert_eq!(forecast(Lines(10.), 0., 3600), Lines(10.));
+        // une production négative ne descend pas sous zéro
+        assert_eq!(forecast(Lines(10.), -1., 60), Lines::ZERO);
+        let far = forecast(Lines(0.), 1e9, MAX_FORECAST_SECS);
+        assert_eq!(format_large(far.0, 2), "3.154e16");
+    }
+
+    #[test]
+    fn forecast_keys_double_and_halve_the_horizon() {
+        let mut app = game(Config {
+            forecast_secs: 60,
+            ..Config::default()
+        });
+        handle_key(&mut app, press(KeyCode::Char('+')));
+        assert_eq!(app.forecast_secs, 120);
+        for _ in 0..10 {
+            handle_key(&mut app, press(KeyCode::Char('-')));
+        }
+        assert_eq!(app.forecast_secs, 1);
+    }
+
+    #[test]
+    fn synergy_needs_its_partner() {
+        let items = r#"{
+            "items": [
+                {"cps": 1, "cost": 5, "name": "dev", "long_name": "developer"},
+                {"cps": 2, "cost": 10, "name": "git", "long_name": "version control"}
+            ],
+            "synergies": [{"source_id": 0, "target_id": 1, "per_unit_bonus": 0.1}]
+        }"#;
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game_of(items, config);
+        assert_eq!(app.total_cps(), 4.);
+        app.owned_items.insert(ItemId(0), 5.);
+        // 5 dev, 1 ligne chacun, et 2 git boostés de 50 %
+        assert!((app.total_cps() - (5. + 2. * 2. * 1.5)).abs() < 1e-9);
+        assert!((app.unit_cps(ItemId(1)) - 3.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn synergies_must_refer_to_items() {
+        let items = r#"{
+            "items": [{"cps": 1, "cost": 5, "name": "dev", "long_name": "developer"}],
+            "synergies": [{"source_id": 0, "target_id": 3, "per_unit_bonus": 0.1}]
+        }"#;
+        assert!(matches!(
+            App::with_catalog(Config::default(), items, "items.json"),
+            Err(ClidleError::SynergyItemMissing(0, 3))
+        ));
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_configured_start() {
+        let mut config = Config {
+            starting_lines: Lines(50.),
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 2);
+        let mut app = game(config);
+        buy_item(&mut app, "git 3".to_string()).unwrap();
+        app.update(100);
+        app.commits = 4;
+        handle_key(&mut app, press(KeyCode::Char('n')));
+        let state = handle_key(&mut app, press(KeyCode::Char('y')));
+        assert!(play(&mut app, state));
+        assert_eq!(app.code_lines, Lines(50.));
+        assert_eq!(app.owned_items, HashMap::from([(ItemId(0), 2.)]));
+        assert_eq!(app.lifetime_lines, Lines::ZERO);
+        assert_eq!(app.lifetime_purchases, 0);
+        assert_eq!(app.played_secs, 0);
+        assert!(app.history.last().is_none());
+        // contrairement au prestige, rien n'est gardé
+        assert_eq!(app.commits, 0);
+    }
+
+    #[test]
+    fn reset_needs_a_confirmation() {
+        let mut app = game(Config::default());
+        app.code_lines = Lines(30.);
+        handle_key(&mut app, press(KeyCode::Char('n')));
+        let state = handle_key(&mut app, press(KeyCode::Char('n')));
+        assert!(matches!(state, GameState::Noop));
+        assert_eq!(app.code_lines, Lines(30.));
+    }
+
+    #[test]
+    fn long_input_shows_its_end() {
+        assert_eq!(visible_tail("buy dev", 20), "buy dev");
+        // une colonne reste pour le curseur
+        assert_eq!(visible_tail("abcdefghij", 5), "ghij");
+        assert_eq!(visible_tail("abcdefghij", 1), "");
+        assert_eq!(visible_tail("", 0), "");
+        // les caractères larges comptent pour deux colonnes
+        assert_eq!(visible_tail("日本語です", 6), "です");
+        assert_eq!(visible_tail("a日本", 4), "本");
+    }
+
+    #[test]
+    fn click_tiers_multiply_the_click() {
+        let mut app = game(Config {
+            starting_lines: Lines(1200.),
+            ..Config::default()
+        });
+        assert_eq!(app.click_power(), 1.);
+        upgrade_click(&mut app).unwrap();
+        assert_eq!((app.click_tier, app.click_power()), (1, 2.));
+        upgrade_click(&mut app).unwrap();
+        assert_eq!((app.click_tier, app.click_power()), (2, 6.));
+        assert_eq!(app.code_lines, Lines(100.));
+        assert!(matches!(
+            upgrade_click(&mut app),
+            Err(ClidleError::ClickTierTooExpensive(10_000, _))
+        ));
+        app.produce(Lines(app.click_power()));
+        assert_eq!(app.code_lines, Lines(106.));
+        app.click_tier = app.config.click_tiers.len();
+        assert!(matches!(
+            upgrade_click(&mut app),
+            Err(ClidleError::NoClickTierLeft)
+        ));
+    }
+
+    #[test]
+    fn thresholds_fire_once_each() {
+        let mut app = game(Config {
+            thresholds: vec![100, 1000],
+            ..Config::default()
+        });
+        app.produce(Lines(150.));
+        assert_eq!(app.reached_threshold, 100);
+        app.produce(Lines(900.));
+        assert_eq!(app.reached_threshold, 1000);
+        assert_eq!(app.stats.thresholds_reached, 2);
+        // dépenser puis repasser au-dessus ne fête rien de nouveau
+        app.code_lines = Lines(50.);
+        app.produce(Lines(2000.));
+        assert_eq!(app.stats.thresholds_reached, 2);
+        let celebrations = app
+            .log
+            .iter()
+            .filter(|entry| entry.contains("1000"))
+            .count();
+        assert_eq!(celebrations, 1);
+        assert!(app.highlight_until.is_some());
+    }
+
+    #[test]
+    fn starting_lines_are_not_celebrated() {
+        let mut app = game(Config {
+            starting_lines: Lines(500.),
+            thresholds: vec![100, 1000],
+            ..Config::default()
+        });
+        app.produce(Lines(1.));
+        assert_eq!(app.stats.thresholds_reached, 0);
+        assert!(app.log.is_empty());
+    }
+
+    #[test]
+    fn invariants_catch_corrupted_states() {
+        let items = r#"[{"cps": 1, "cost": 5, "name": "dev", "long_name": "dev", "max": 3}]"#;
+        let healthy = || {
+            let mut app = game_of(items, Config::default());
+            app.owned_items.insert(ItemId(0), 2.);
+            app
+        };
+        assert_eq!(healthy().check_invariants(), Ok(()));
+        let corruptions: [fn(&mut App); 6] = [
+            |app| app.code_lines = Lines(-1.),
+            |app| app.code_lines = Lines(f64::NAN),
+            |app| {
+                app.owned_items.insert(ItemId(9), 1.);
+            },
+            |app| {
+                app.owned_items.insert(ItemId(0), 1.5);
+            },
+            |app| {
+                app.owned_items.insert(ItemId(0), 4.);
+            },
+            |app| app.click_tier = 99,
+        ];
+        for corrupt in corruptions {
+            let mut app = healthy();
+            corrupt(&mut app);
+            assert!(app.check_invariants().is_err());
+        }
+    }
+
+    #[test]
+    fn invariant_report_goes_to_the_log() {
+        let mut app = game(Config::default());
+        app.debug = Some(DebugInfo::default());
+        app.code_lines = Lines(-5.);
+        handle_key(&mut app, press(KeyCode::F(11)));
+        assert!(app.log.last().unwrap().contains("code lines are -5"));
+    }
+
+    #[test]
+    fn preview_computes_a_hypothetical_purchase() {
+        let mut config = Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        };
+        config.starting_items.insert("dev".to_string(), 10);
+        let mut app = game(config);
+        app.input_mode = InputMode::Buy;
+        app.input = "git 2".to_string();
+        let preview = preview_buy(&app).unwrap();
+        assert_eq!((preview.name.as_str(), preview.count), ("git", 2.));
+        assert_eq!(preview.lines, (Lines(100.), Lines(80.)));
+        assert!((preview.cps.0 - 1.).abs() < 1e-9 && (preview.cps.1 - 2.).abs() < 1e-9);
+        // 80 lignes paient déjà un git de plus
+        assert_eq!(preview.next_in, Some(0.));
+        // rien n'a été acheté
+        assert_eq!(app.code_lines, Lines(100.));
+        assert_eq!(app.owned_count(ItemId(2)), 0.);
+        assert!((app.cps_with(&HashMap::from([(ItemId(2), 4.)])) - 2.).abs() < 1e-9);
+        app.input = "ghost".to_string();
+        assert!(preview_buy(&app).is_none());
+    }
+
+    #[test]
+    fn free_items() {
+        let items = r#"[
+            {"cps": 1, "cost": 0, "name": "intern", "long_name": "intern"},
+            {"cps": 1, "cost": 0, "name": "bot", "long_name": "bot", "max": 5},
+            {"cps": 5, "cost": 10, "name": "dev", "long_name": "developer"}
+        ]"#;
+        let mut app = game_of(items, Config::default());
+        buy_item(&mut app, "intern".to_string()).unwrap();
+        // sans max, un gratuit s'achète un à la fois
+        buy_item(&mut app, "intern all".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(0)), 2.);
+        buy_item(&mut app, "bot max".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(1)), 5.);
+        assert_eq!(app.code_lines, Lines::ZERO);
+        let free = comparison(&app, ItemId(0));
+        assert_eq!(
+            (free.price, free.efficiency, free.break_even),
+            (0, None, Some(0.))
+        );
+        assert_eq!(best_purchase(&app), Some(ItemId(0)));
+    }
+
+    /// Drive the time of `app` by hand.
+    fn manual_clock(app: &mut App) -> Rc<ManualClock> {
+        let clock = Rc::new(ManualClock::new());
+        app.set_clock(clock.clone());
+        clock
+    }
+
+    #[test]
+    fn nothing_is_produced_while_paused() {
+        let mut config = Config {
+            start_paused: true,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        let clock = manual_clock(&mut app);
+        let mut last_tick = clock.now();
+        for _ in 0..5 {
+            clock.advance(Duration::from_secs(2));
+            assert!(!tick(&mut app, &mut last_tick));
+        }
+        assert_eq!(app.code_lines, Lines::ZERO);
+        assert_eq!(app.played_secs, 0);
+        // la reprise ne rattrape pas le temps passé en pause
+        handle_key(&mut app, press(KeyCode::Char('p')));
+        clock.advance(Duration::from_secs(1));
+        assert!(tick(&mut app, &mut last_tick));
+        assert_eq!(app.code_lines, Lines(1.));
+    }
+
+    #[test]
+    fn the_worst_performer_is_sold_first() {
+        let mut app = game(Config::default());
+        assert_eq!(worst_performer(&app), None);
+        sell_worst(&mut app).unwrap();
+        assert_eq!(
+            app.log.last().map(String::as_str),
+            Some(t(Msg::NothingToSell, app.lang))
+        );
+        let mut config = Config::default();
+        config.starting_items.insert("dev".to_string(), 1);
+        config.starting_items.insert("git".to_string(), 2);
+        config.starting_items.insert("f".to_string(), 1);
+        let mut app = game(config);
+        // dev produit 0.1, git 2 * 0.5, f 2 : dev part en premier
+        assert_eq!(worst_performer(&app), Some(ItemId(0)));
+        sell_worst(&mut app).unwrap();
+        assert_eq!(app.owned_count(ItemId(0)), 0.);
+        // dev n'est plus possédé, git devient le pire
+        assert_eq!(worst_performer(&app), Some(ItemId(2)));
+        sell_worst(&mut app).unwrap();
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        assert_eq!(worst_performer(&app), Some(ItemId(2)));
+    }
+
+    #[test]
+    fn buying_with_nan_negative_or_huge_code_lines() {
+        let mut app = game(Config::default());
+        app.code_lines = Lines(f64::NAN);
+        assert!(matches!(
+            buy_item(&mut app, "dev".to_string()),
+            Err(ClidleError::LinesNotANumber)
+        ));
+        assert_eq!(app.owned_count(ItemId(0)), 0.);
+
+        app.code_lines = Lines(-100.);
+        assert!(matches!(
+            buy_item(&mut app, "dev".to_string()),
+            Err(ClidleError::NotEnoughLines(_, 5, _))
+        ));
+        assert_eq!(app.code_lines, Lines(-100.));
+
+        // bien au-delà de u64::MAX, la comparaison ne sature pas
+        app.code_lines = Lines(1e30);
+        buy_item(&mut app, "f 2".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(1)), 2.);
+        assert!(app.code_lines > Lines(u64::MAX as f64));
+    }
+
+    /// Keys typed by a test instead of a player, in order.
+    struct Scripted(std::collections::VecDeque<KeyEvent>);
+
+    impl Input for Scripted {
+        fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+            Ok(!self.0.is_empty())
+        }
+
+        fn read(&mut self) -> io::Result<Option<KeyEvent>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    /// `Scripted` typing each char of `keys`, `\n` being Enter, `\t` Tab and `\x1b` Esc.
+    fn typing(keys: &str) -> Scripted {
+        Scripted(
+            keys.chars()
+                .map(|c| match c {
+                    '\n' => press(KeyCode::Enter),
+                    '\t' => press(KeyCode::Tab),
+                    '\x1b' => press(KeyCode::Esc),
+                    c => press(KeyCode::Char(c)),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn a_replay_ends_in_the_recorded_state_with_the_recorded_items() {
+        let mut app = game(Config::default());
+        let clock = manual_clock(&mut app);
+        app.recording = Some(Recording::new(
+            app.config.clone(),
+            ITEMS.to_string(),
+            app.snapshot(),
+        ));
+        let mut last_tick = clock.now();
+        // le dernier achat passe par le compteur : Tab, + puis Entrée
+        let mut input =
+            typing("cccccccccccccccbdev\n\x1bcccbgit\n\x1bccccccccccccccccccccccbdev\t+\n\x1b");
+        while input.poll(Duration::ZERO).unwrap() {
+            let state = handle_input(&mut app, &mut input).unwrap();
+            assert!(play(&mut app, state));
+            clock.advance(Duration::from_millis(400));
+            tick(&mut app, &mut last_tick);
+        }
+        let mut recording = app.recording.take().unwrap();
+        let keys = recording
+            .events
+            .iter()
+            .filter(|event| event.key_event().is_some())
+            .count();
+        assert_eq!(keys, 60);
+        let end = app.snapshot();
+        assert_eq!(end.owned_items.len(), 2);
+        assert_eq!(app.owned_count(ItemId(0)), 3.);
+        // l'items.json du moment n'est pas lu, ce sont les items enregistrés qui comptent
+        assert_eq!(replay(&recording, "does/not/exist.json").unwrap(), end);
+        recording.catalog = None;
+        assert!(matches!(
+            replay(&recording, "does/not/exist.json"),
+            Err(ClidleError::ItemsUnreadable(..))
+        ));
+    }
+
+    #[test]
+    fn hardcore_rejects_selling_and_undoing() {
+        let mut config = Config {
+            hardcore: true,
+            starting_lines: Lines(20.),
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 1);
+        let mut app = game(config);
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        let before = app.snapshot();
+
+        assert!(matches!(
+            handle_key(&mut app, press(KeyCode::Char('s'))),
+            GameState::Noop
+        ));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(matches!(app.error, Err(ClidleError::ForbiddenInHardcore)));
+        assert!(matches!(
+            sell_item(&mut app, "git".to_string()),
+            Err(ClidleError::ForbiddenInHardcore)
+        ));
+        assert!(matches!(
+            sell_worst(&mut app),
+            Err(ClidleError::ForbiddenInHardcore)
+        ));
+
+        app.error = Ok(());
+        let state = handle_key(&mut app, press(KeyCode::Char('u')));
+        assert!(play(&mut app, state));
+        assert!(matches!(app.error, Err(ClidleError::ForbiddenInHardcore)));
+        // rien n'a été vendu ni remboursé
+        assert_eq!(app.snapshot(), before);
+    }
+
+    #[test]
+    fn peak_cps_never_decreases_after_selling() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 4);
+        let mut app = game(config);
+        app.update(1);
+        assert_eq!(app.peak_cps, 2.);
+        sell_item(&mut app, "git 3".to_string()).unwrap();
+        assert_eq!(app.total_cps(), 0.5);
+        app.update(1);
+        assert_eq!(app.peak_cps, 2.);
+        // et survit à la sauvegarde
+        let mut restored = game(Config::default());
+        restored.restore(app.snapshot());
+        assert_eq!(restored.peak_cps, 2.);
+        restored.update(1);
+        assert_eq!(restored.peak_cps, 2.);
+    }
+
+    #[test]
+    fn item_templates_are_filled_with_the_current_values() {
+        let items = r#"[
+            {"cps": 0.1, "cost": 5, "name": "dev", "long_name": "developer"},
+            {"cps": 0.5, "cost": 10, "name": "git", "long_name": "version control system",
+             "display_template": "{name}: {price} lines, {owned} owned, {oops}"}
+        ]"#;
+        let mut config = Config {
+            item_template: Some("{long_name} makes {cps}".to_string()),
+            dynamic_cost: true,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 3);
+        let app = game_of(items, config);
+        let git = &app.items_index[1];
+        // le prix affiché suit la production, pas le coût de base
+        let price = app.price_of(git);
+        assert!(price > git.cost);
+        // un emplacement inconnu reste tel quel, pour voir la faute de frappe
+        assert_eq!(
+            item_line(&app, git),
+            format!("git: {price} lines, 3 owned, {{oops}}")
+        );
+        // sans son propre modèle, celui de la config, sans prix puisqu'il n'en demande pas
+        assert_eq!(
+            item_line(&app, &app.items_index[0]),
+            format!("developer makes {}", format_rate(&app, 0.1))
+        );
+        assert_eq!(fill_template("{a}{b}{a}", &[("a", &1), ("c", &3)]), "1{b}1");
+    }
+
+    /// Feed each key of `keys` to the game like `run_app` does, `\n` being Enter.
+    fn type_keys(app: &mut App, keys: &str) {
+        let mut input = typing(keys);
+        while input.poll(Duration::ZERO).unwrap() {
+            let state = handle_input(app, &mut input).unwrap();
+            play(app, state);
+        }
+    }
+
+    #[test]
+    fn only_purchases_above_the_threshold_are_confirmed() {
+        let config = Config {
+            confirm_above: Some(100),
+            starting_lines: Lines(10_000.),
+            ..Config::default()
+        };
+        let mut app = game(config);
+        type_keys(&mut app, "bdev\n");
+        assert!(matches!(app.input_mode, InputMode::Buy));
+        assert_eq!(app.owned_count(ItemId(0)), 1.);
+
+        type_keys(&mut app, "f\n");
+        assert!(matches!(
+            app.input_mode,
+            InputMode::ConfirmBuy { price: 3000, .. }
+        ));
+        assert_eq!(app.owned_count(ItemId(1)), 0.);
+        // la confirmation ramène au mode d'achat
+        type_keys(&mut app, "y");
+        assert!(matches!(app.input_mode, InputMode::Buy));
+        assert_eq!(app.owned_count(ItemId(1)), 1.);
+
+        // et à la ligne de commande, que l'achat soit confirmé ou annulé
+        type_keys(&mut app, "\x1b:buy f\n");
+        assert!(matches!(app.input_mode, InputMode::ConfirmBuy { .. }));
+        type_keys(&mut app, "n");
+        assert!(matches!(app.input_mode, InputMode::Command));
+        assert_eq!(app.owned_count(ItemId(1)), 1.);
+    }
+
+    #[test]
+    fn an_imported_save_starts_a_fresh_session() {
+        let mut config = Config {
+            starting_lines: Lines(1000.),
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut exported = game(config);
+        buy_item(&mut exported, "dev 3".to_string()).unwrap();
+        exported.update(5);
+        let code = exported.snapshot().export().unwrap();
+
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        });
+        buy_item(&mut app, "git".to_string()).unwrap();
+        app.update(3);
+        import_save(&mut app, &code).unwrap();
+        assert_eq!(app.snapshot(), exported.snapshot());
+        // l'achat d'avant l'import ne peut plus être annulé
+        assert!(app.history.last().is_none());
+        assert_eq!(app.stats.purchases, 0);
+        assert_eq!(app.stats.lines_produced, Lines::ZERO);
+        assert!(app
+            .owned_samples
+            .iter()
+            .all(|samples| samples.values().is_empty()));
+        assert_eq!(app.gains.rate(app.clock.now()), None);
+    }
+
+    #[test]
+    fn a_corrupt_save_is_not_imported() {
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        });
+        buy_item(&mut app, "git".to_string()).unwrap();
+        let before = app.snapshot();
+        let code = before.export().unwrap();
+        for corrupt in ["not base64!", &code[..code.len() / 2], "e30K", ""] {
+            assert!(
+                matches!(
+                    import_save(&mut app, corrupt),
+                    Err(ClidleError::ImportInvalid(_))
+                ),
+                "{corrupt}"
+            );
+        }
+        assert_eq!(app.snapshot(), before);
+        assert!(app.history.last().is_some());
+    }
+
+    #[test]
+    fn average_rate_over_the_time_played() {
+        assert_eq!(average_rate(Lines(100.), 0), None);
+        assert_eq!(average_rate(Lines::ZERO, 0), None);
+        assert_eq!(average_rate(Lines(100.), 40), Some(2.5));
+        assert_eq!(average_rate(Lines::ZERO, 10), Some(0.));
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        for _ in 0..4 {
+            app.update(1);
+        }
+        assert_eq!(app.played_secs, 4);
+        assert_eq!(
+            average_rate(app.lifetime_lines, app.played_secs),
+            Some(app.lifetime_lines.0 / 4.)
+        );
+    }
+
+    #[test]
+    fn item_rows_line_up_with_wide_icons_and_marks() {
+        let items = r#"[
+            {"cps": 0.1, "cost": 5, "name": "dev", "long_name": "developer", "icon": "👩"},
+            {"cps": 2, "cost": 3000, "name": "f", "long_name": "feature", "icon": "*"},
+            {"cps": 0.5, "cost": 10, "name": "git", "long_name": "version control system"}
+        ]"#;
+        let mut app = game_of(items, Config::default());
+        assert_eq!(icon_cell("👩", 2), "👩 ");
+        assert_eq!(icon_cell("*", 2), "*  ");
+        assert_eq!(icon_cell("", 2), "   ");
+        app.favorites.insert(ItemId(1));
+        for best in [None, Some(ItemId(0)), Some(ItemId(2))] {
+            let starts: Vec<usize> = app
+                .items_index
+                .iter()
+                .map(|item| {
+                    let row = item_row(&app, item, best, Some(2));
+                    row.width() - item_line(&app, item).width()
+                })
+                .collect();
+            // la marque, l'étoile et l'icône prennent la même place sur chaque ligne
+            let expected = if best.is_some() { 7 } else { 5 };
+            assert_eq!(starts, [expected; 3], "{best:?}");
+        }
+    }
+
+    #[test]
+    fn non_finite_production_is_reported_once_and_not_produced() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        app.items_index[2].cps = f64::NAN;
+        let logged = app.log.len();
+        for _ in 0..3 {
+            app.update(1);
+        }
+        assert_eq!(app.code_lines, Lines::ZERO);
+        assert_eq!(app.log.len(), logged + 1);
+        assert_eq!(
+            app.log.last().unwrap(),
+            &tf(
+                Msg::ErrorLine,
+                app.lang,
+                &[&ClidleError::ProductionNotFinite(f64::NAN).localized(app.lang)]
+            )
+        );
+        // une fois l'item réparé la production reprend
+        app.items_index[2].cps = 0.5;
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(1.));
+
+        // des lignes qui débordent restent au plus grand nombre possible
+        app.code_lines = Lines(f64::MAX);
+        app.items_index[2].cps = f64::MAX / 4.;
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(f64::MAX));
+        assert_eq!(
+            app.log.last().unwrap(),
+            &tf(
+                Msg::ErrorLine,
+                app.lang,
+                &[&ClidleError::LinesNotFinite(f64::INFINITY).localized(app.lang)]
+            )
+        );
+    }
+
+    #[test]
+    fn bulk_purchases_log_their_price_and_count() {
+        let mut config = Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 1);
+        let mut app = game(config);
+        buy_item(&mut app, "git 3".to_string()).unwrap();
+        assert_eq!(app.code_lines, Lines(70.));
+        assert_eq!(
+            app.log.last().unwrap(),
+            "Bought 3 git for 30 lines, 10 each (now own 4)"
+        );
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        assert_eq!(
+            app.log.last().unwrap(),
+            "Bought 1 dev for 5 lines (now own 1)"
+        );
+    }
+
+    /// What `ui` draws for `app` on a `width` by `height` terminal, row by row.
+    fn screen(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(tui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn focus_mode_shows_the_last_error() {
+        let mut app = game(Config::default());
+        type_keys(&mut app, "f");
+        assert!(app.focus_mode);
+        type_keys(&mut app, "bgit\n");
+        assert!(matches!(app.error, Err(ClidleError::NotEnoughLines(..))));
+        let error = error_line(&app).unwrap();
+        assert!(screen(&mut app, 100, 20)
+            .iter()
+            .any(|row| row.contains(&error)));
+        // l'achat marche aussi sans les autres panneaux
+        app.code_lines = Lines(20.);
+        type_keys(&mut app, "git\n");
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        assert_eq!(error_line(&app), None);
+    }
+
+    #[test]
+    fn saves_follow_their_items_when_ids_shift() {
+        let mut config = Config::default();
+        config.starting_items.insert("dev".to_string(), 2);
+        config.starting_items.insert("git".to_string(), 3);
+        let save = game(config).snapshot();
+        let inserted = r#"[
+            {"cps": 0.05, "cost": 2, "name": "intern", "long_name": "intern"},
+            {"cps": 0.1, "cost": 5, "name": "dev", "long_name": "developer"},
+            {"cps": 2, "cost": 3000, "name": "f", "long_name": "feature"},
+            {"cps": 0.5, "cost": 10, "name": "git", "long_name": "version control system"}
+        ]"#;
+        let mut app = game_of(inserted, Config::default());
+        app.restore(save);
+        assert_eq!(
+            app.owned_items,
+            HashMap::from([(ItemId(1), 2.), (ItemId(3), 3.)])
+        );
+        assert_eq!(app.code_lines, Lines::ZERO);
+    }
+
+    #[test]
+    fn unknown_ids_of_a_save_without_catalog_are_dropped() {
+        let mut save = game(Config::default()).snapshot();
+        save.catalog.clear();
+        save.owned_items = HashMap::from([(ItemId(2), 1.), (ItemId(3), 4.), (ItemId(9), 1.)]);
+        let mut app = game(Config::default());
+        let logged = app.log.len();
+        app.restore(save);
+        assert_eq!(app.owned_items, HashMap::from([(ItemId(2), 1.)]));
+        assert_eq!(app.log.len(), logged + 2);
+        // rien ne plante plus loin sur un id inconnu
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(0.5));
+    }
+
+    #[test]
+    fn prices_grow_with_the_production_up_to_a_cap() {
+        assert_eq!(price_at(100, 1000., false), 100);
+        assert_eq!(price_at(100, 0., true), 100);
+        assert_eq!(price_at(100, -5., true), 100);
+        assert_eq!(price_at(100, f64::NAN, true), 100);
+        // cost * sqrt(1 + cps / 100), arrondi au-dessus
+        assert_eq!(price_at(100, 100., true), 142);
+        assert_eq!(price_at(100, 300., true), 200);
+        let prices: Vec<u64> = [1., 10., 100., 1000., 10_000.]
+            .iter()
+            .map(|&cps| price_at(100, cps, true))
+            .collect();
+        assert!(
+            prices.windows(2).all(|pair| pair[0] < pair[1]),
+            "{prices:?}"
+        );
+        // au-delà, le facteur plafonne
+        assert_eq!(price_at(100, 1e12, true), 1000);
+        assert_eq!(price_at(100, f64::INFINITY, true), 1000);
+        assert_eq!(price_at(u64::MAX, 1e12, true), u64::MAX);
+
+        let mut config = Config {
+            dynamic_cost: true,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        let before = app.price_of(&app.items_index[1]);
+        app.owned_items.insert(ItemId(1), 10.);
+        assert!(app.price_of(&app.items_index[1]) > before);
+    }
+
+    #[test]
+    fn submitting_nothing_is_not_an_error() {
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        });
+        // le second Entrée d'un double appui arrive sur une saisie vide
+        type_keys(&mut app, "bgit\n\n   \n");
+        assert!(matches!(app.input_mode, InputMode::Buy));
+        assert!(app.error.is_ok());
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        for keys in ["\x1bsgit\n\n", "\x1b:\n", "\x1b/\n"] {
+            type_keys(&mut app, keys);
+            assert!(app.error.is_ok(), "{keys:?}");
+        }
+        assert_eq!(app.owned_count(ItemId(2)), 0.);
+    }
+
+    #[test]
+    fn help_verbosity_sets_the_spans_of_the_help_line() {
+        let spans = |verbosity| {
+            let app = game(Config {
+                help_verbosity: verbosity,
+                ..Config::default()
+            });
+            let (help, _) = help_line(&app);
+            help.into_iter()
+                .map(|span| span.content.into_owned())
+                .collect::<Vec<_>>()
+        };
+        let full = spans(HelpVerbosity::Full);
+        let short = spans(HelpVerbosity::Short);
+        let hidden = spans(HelpVerbosity::Hidden);
+        let keys = (full.len() - 1) / 2;
+        assert!(keys > 10);
+        // le compteur, puis chaque touche suivie d'un espace
+        assert_eq!(short.len(), 1 + 2 * keys);
+        assert_eq!(short[0], full[0]);
+        assert!(short[1..].chunks(2).all(|pair| pair[1] == " "));
+        assert_eq!(hidden.len(), 4);
+        assert_eq!(hidden[0], full[0]);
+        assert_eq!(hidden[2], "q");
+        // les consignes des autres modes ne sont jamais raccourcies
+        let mut app = game(Config {
+            help_verbosity: HelpVerbosity::Hidden,
+            ..Config::default()
+        });
+        app.input_mode = InputMode::Buy;
+        let buying = help_line(&app).0.len();
+        app.config.help_verbosity = HelpVerbosity::Full;
+        assert_eq!(help_line(&app).0.len(), buying);
+    }
+
+    #[test]
+    fn fractional_items_produce_their_share() {
+        let config = Config {
+            fractional_items: true,
+            starting_lines: Lines(100.),
+            ..Config::default()
+        };
+        let mut app = game(config);
+        buy_item(&mut app, "git 0.5".to_string()).unwrap();
+        buy_item(&mut app, "dev 2.5".to_string()).unwrap();
+        // 10 * 0.5 puis 5 * 2.5 arrondi au-dessus
+        assert_eq!(app.code_lines, Lines(100. - 5. - 13.));
+        assert_eq!(app.total_cps(), 0.5 * 0.5 + 2.5 * 0.1);
+        let before = app.code_lines;
+        app.update(4);
+        assert!((app.code_lines - before - Lines(2.)).0.abs() < 1e-9);
+        sell_item(&mut app, "git 0.25".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(2)), 0.25);
+
+        // une quantité infime compte pour COUNT_EPSILON, et reste possédée une fois payée
+        let before = app.code_lines;
+        buy_item(&mut app, "f 1e-300".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(1)), COUNT_EPSILON);
+        assert_eq!(app.code_lines, before - Lines(1.));
+        sell_item(&mut app, "f 1e-300".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(1)), 0.);
+    }
+
+    #[test]
+    fn doubling_advice_buys_greedily_once_per_tick() {
+        let items = r#"[
+            {"cps": 1, "cost": 10, "name": "a", "long_name": "a"},
+            {"cps": 5, "cost": 100, "name": "b", "long_name": "b"},
+            {"cps": 2, "cost": 15, "name": "c", "long_name": "c", "max": 1}
+        ]"#;
+        let mut app = game_of(items, Config::default());
+        assert!(double_cps_advice(&app).is_none());
+        app.owned_items.insert(ItemId(0), 5.);
+        // c rapporte le plus par ligne mais une seule fois, puis a vaut mieux que b
+        let advice = double_cps_advice(&app).unwrap();
+        assert_eq!(advice.purchases, [(ItemId(2), 1), (ItemId(0), 3)]);
+        assert_eq!(advice.cost, 15 + 3 * 10);
+        // calculé à chaque seconde de jeu et après chaque action, pas à chaque image
+        assert!(app.advice.is_none());
+        app.update(1);
+        assert_eq!(app.advice.as_ref().unwrap().purchases, advice.purchases);
+        app.code_lines = Lines(100.);
+        play(&mut app, GameState::BuyItem("c".to_string()));
+        assert_eq!(app.advice.as_ref().unwrap().purchases, [(ItemId(0), 7)]);
+    }
+
+    #[test]
+    fn every_error_displays_its_context() {
+        let errors: Vec<(ClidleError, &[&str])> = vec![
+            (
+                ClidleError::BuyingItemNotKnown("dve".to_string(), vec!["dev".to_string()]),
+                &["dve", "dev"],
+            ),
+            (
+                ClidleError::SellingItemNotKnown("gti".to_string(), Vec::new()),
+                &["gti"],
+            ),
+            (
+                ClidleError::SellingItemNotOwned("git".to_string(), 2., 3.),
+                &["git", "2", "3"],
+            ),
+            (ClidleError::ItemMaxedOut("f".to_string(), 7), &["f", "7"]),
+            (
+                ClidleError::NotEnoughLines("dev".to_string(), 50, 12),
+                &["dev", "50", "12"],
+            ),
+            (ClidleError::ItemLocked("f".to_string(), 900), &["f", "900"]),
+            (
+                ClidleError::UnlockTooExpensive("f".to_string(), 900, 40),
+                &["f", "900", "40"],
+            ),
+            (
+                ClidleError::ReserveBreached("dev".to_string(), 50, 30),
+                &["dev", "50", "30"],
+            ),
+            (
+                ClidleError::OverBudget("dev".to_string(), 50, 25),
+                &["dev", "50", "25"],
+            ),
+            (ClidleError::NoPurchaseToUndo, &[]),
+            (ClidleError::NoClickTierLeft, &[]),
+            (ClidleError::ClickTierTooExpensive(100, 60), &["100", "60"]),
+            (ClidleError::LinesNotANumber, &[]),
+            (ClidleError::ProductionNotFinite(f64::INFINITY), &["inf"]),
+            (ClidleError::LinesNotFinite(f64::NAN), &["NaN"]),
+            (ClidleError::ForbiddenInHardcore, &[]),
+            (ClidleError::ForbiddenInIronman, &[]),
+            (
+                ClidleError::KeyConflict('x', "buy".to_string(), "sell".to_string()),
+                &["x", "buy", "sell"],
+            ),
+            (ClidleError::NothingToPrestige(1_000_000), &["1000000"]),
+            (
+                ClidleError::UpgradeNotKnown("turbo".to_string()),
+                &["turbo"],
+            ),
+            (
+                ClidleError::UpgradeAlreadyBought("turbo".to_string()),
+                &["turbo"],
+            ),
+            (ClidleError::NoFavorite, &[]),
+            (
+                ClidleError::NotEnoughCommits("turbo".to_string(), 5, 2),
+                &["turbo", "5", "2"],
+            ),
+            (
+                ClidleError::ItemsUnreadable("items.json".to_string(), "denied".to_string()),
+                &["items.json", "denied"],
+            ),
+            (
+                ClidleError::ItemsFileMalformed(
+                    "items.json".to_string(),
+                    3,
+                    14,
+                    "missing field `cps`".to_string(),
+                ),
+                &["items.json", "3", "14", "missing field `cps`"],
+            ),
+            (
+                ClidleError::RecordingUnreadable("game.rec".to_string(), "denied".to_string()),
+                &["game.rec", "denied"],
+            ),
+            (ClidleError::UnknownCommand("dance".to_string()), &["dance"]),
+            (
+                ClidleError::MacroStepFailed(2, Box::new(ClidleError::NoFavorite)),
+                &["2", "favorite"],
+            ),
+            (ClidleError::MacroStepUnconfirmed(3), &["3"]),
+            (ClidleError::MissingItem, &[]),
+            (ClidleError::InvalidQuantity("-3".to_string()), &["-3"]),
+            (
+                ClidleError::ImportInvalid("not base64".to_string()),
+                &["not base64"],
+            ),
+            (ClidleError::SynergyItemMissing(4, 9), &["4", "9"]),
+            (
+                ClidleError::ConfigMalformed("bad".to_string()),
+                &[CONFIG_PATH, "bad"],
+            ),
+            (
+                ClidleError::SaveMalformed("bad".to_string()),
+                &[SAVE_PATH, "bad"],
+            ),
+            (
+                ClidleError::SaveFailed("full".to_string()),
+                &[SAVE_PATH, "full"],
+            ),
+            (
+                ClidleError::UnknownArgument("--fast".to_string()),
+                &["--fast"],
+            ),
+            (
+                ClidleError::ArgumentNeedsValue("--items".to_string()),
+                &["--items"],
+            ),
+        ];
+        let mut messages = Vec::new();
+        for (error, context) in &errors {
+            let message = error.to_string();
+            assert!(!message.is_empty() && !message.contains("{}"), "{error:?}");
+            for part in *context {
+                assert!(message.contains(part), "{message:?} lacks {part:?}");
+            }
+            assert_eq!(message, error.localized(Lang::English));
+            assert_ne!(message, error.localized(Lang::French), "{error:?}");
+            messages.push(message);
+        }
+        // chaque variante a son propre message
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), errors.len());
+        assert!(
+            ClidleError::BuyingItemNotKnown("dve".to_string(), Vec::new())
+                .to_string()
+                .ends_with("dve")
+        );
+    }
+
+    #[test]
+    fn a_long_sleep_is_credited_capped_and_at_once() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        let clock = manual_clock(&mut app);
+        app.recording = Some(Recording::new(
+            app.config.clone(),
+            ITEMS.to_string(),
+            app.snapshot(),
+        ));
+        let mut last_tick = clock.now();
+        clock.advance(Duration::from_secs(10 * 3600));
+        assert!(tick(&mut app, &mut last_tick));
+        // une heure au plus, à moitié prix hors du jeu
+        assert_eq!(app.played_secs, 1800);
+        assert_eq!(app.code_lines, Lines(1800.));
+        assert_eq!(last_tick, clock.now());
+        // crédité d'un coup, pas seconde par seconde
+        assert_eq!(
+            app.recording.as_ref().unwrap().events,
+            [RecordedEvent::CatchUp { secs: 1800 }]
+        );
+        assert!(!tick(&mut app, &mut last_tick));
+    }
+
+    #[test]
+    fn idle_timeout_only_fires_after_the_last_key() {
+        let mut app = game(Config {
+            idle_quit_secs: Some(60),
+            ..Config::default()
+        });
+        let clock = manual_clock(&mut app);
+        type_keys(&mut app, "c");
+        let expired = |app: &App| {
+            idle_expired(
+                app.last_activity,
+                app.clock.now(),
+                app.config.idle_quit_secs,
+            )
+        };
+        clock.advance(Duration::from_secs(59));
+        assert!(!expired(&app));
+        // jouer repousse la fin
+        type_keys(&mut app, "c");
+        clock.advance(Duration::from_secs(59));
+        assert!(!expired(&app));
+        clock.advance(Duration::from_secs(1));
+        assert!(expired(&app));
+        assert!(!idle_expired(
+            app.last_activity,
+            clock.now() + Duration::from_secs(3600),
+            None
+        ));
+    }
+
+    #[test]
+    fn the_bell_rings_on_big_events_only_with_bell() {
+        let mut out = Vec::new();
+        ring_bell(&mut out).unwrap();
+        assert_eq!(out, b"\x07");
+
+        let config = Config {
+            bell: true,
+            thresholds: Vec::new(),
+            starting_lines: Lines(500.),
+            ..Config::default()
+        };
+        let mut app = game(config.clone());
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        assert!(!app.ring);
+        upgrade_click(&mut app).unwrap();
+        assert!(std::mem::take(&mut app.ring));
+        app.lifetime_lines = Lines(4_000_000.);
+        app.prestige().unwrap();
+        assert!(std::mem::take(&mut app.ring));
+        buy_upgrade(&mut app, "tests").unwrap();
+        assert!(std::mem::take(&mut app.ring));
+
+        let mut app = game(Config {
+            bell: false,
+            ..config
+        });
+        upgrade_click(&mut app).unwrap();
+        app.lifetime_lines = Lines(4_000_000.);
+        app.prestige().unwrap();
+        assert!(!app.ring);
+    }
+
+    #[test]
+    fn the_best_pick_is_the_most_production_per_line_affordable() {
+        let items = r#"[
+            {"cps": 1, "cost": 10, "name": "a", "long_name": "a"},
+            {"cps": 2, "cost": 20, "name": "b", "long_name": "b"},
+            {"cps": 9, "cost": 50, "name": "c", "long_name": "c"},
+            {"cps": 0, "cost": 1, "name": "d", "long_name": "d"}
+        ]"#;
+        let mut app = game_of(items, Config::default());
+        // rien d'abordable
+        assert_eq!(best_purchase(&app), None);
+        // a et b rapportent autant par ligne, le premier l'emporte
+        app.code_lines = Lines(30.);
+        assert_eq!(best_purchase(&app), Some(ItemId(0)));
+        // c rapporte plus par ligne dès qu'il est abordable
+        app.code_lines = Lines(60.);
+        assert_eq!(best_purchase(&app), Some(ItemId(2)));
+        // un item qui ne produit rien n'est jamais conseillé
+        app.code_lines = Lines(5.);
+        assert_eq!(best_purchase(&app), None);
+    }
+
+    #[test]
+    fn home_and_end_go_to_the_first_and_last_page() {
+        let mut app = game(Config {
+            page_size: 2,
+            ..Config::default()
+        });
+        handle_key(&mut app, press(KeyCode::End));
+        assert_eq!(app.page, 1);
+        handle_key(&mut app, press(KeyCode::End));
+        assert_eq!(app.page, 1);
+        handle_key(&mut app, press(KeyCode::Home));
+        assert_eq!(app.page, 0);
+        // dans les autres modes aussi
+        app.input_mode = InputMode::Buy;
+        handle_key(&mut app, press(KeyCode::End));
+        assert_eq!(app.page, 1);
+        assert!(app.input.is_empty());
+        // une liste d'une seule page reste sur la première
+        let mut app = game(Config::default());
+        handle_key(&mut app, press(KeyCode::End));
+        assert_eq!(app.page, 0);
+        handle_key(&mut app, press(KeyCode::Home));
+        assert_eq!(app.page, 0);
+    }
+
+    #[test]
+    fn productions_are_shown_in_the_rate_unit() {
+        let mut app = game(Config {
+            rate_unit: RateUnit::Minute,
+            ..Config::default()
+        });
+        assert_eq!(format_rate(&app, 0.5), "30.00");
+        assert_eq!(rate_label(&app), t(Msg::PerMinute, app.lang));
+        app.config.rate_unit = RateUnit::Hour;
+        assert_eq!(format_rate(&app, 0.5), "1800.00");
+    }
+
+    #[test]
+    fn a_malformed_catalog_names_the_missing_field_and_its_line() {
+        let items = "[\n    {\"cost\": 5, \"name\": \"dev\", \"long_name\": \"developer\"}\n]";
+        let Err(error) = App::with_catalog(Config::default(), items, "my_items.json") else {
+            panic!("an item without cps was accepted");
+        };
+        let ClidleError::ItemsFileMalformed(path, line, _, reason) = &error else {
+            panic!("{error:?}");
+        };
+        assert_eq!(path, "my_items.json");
+        assert_eq!(*line, 2);
+        assert!(reason.contains("missing field `cps`"), "{reason}");
+        // la position n'est dite qu'une fois, pas aussi à la fin de la raison
+        assert!(!reason.contains(" at line "), "{reason}");
+        let message = error.to_string();
+        assert!(message.contains("my_items.json") && message.contains("`cps`"));
+
+        let items = r#"[{"cps": "fast", "cost": 5, "name": "dev", "long_name": "developer"}]"#;
+        assert!(matches!(
+            App::with_catalog(Config::default(), items, "my_items.json"),
+            Err(ClidleError::ItemsFileMalformed(_, 1, _, reason)) if reason.contains("\"fast\"")
+        ));
+    }
+
+    /// The command `verb item quantity`.
+    fn command(verb: Verb, item: &str, quantity: Quantity) -> Command {
+        Command {
+            verb,
+            item: item.to_string(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn each_verb_of_command_mode_is_parsed() {
+        assert_eq!(
+            parse_action("buy dev 3").unwrap(),
+            Action::Trade(command(Verb::Buy, "dev", Quantity::Count(3.)))
+        );
+        assert_eq!(
+            parse_action("  SELL git  ").unwrap(),
+            Action::Trade(command(Verb::Sell, "git", Quantity::Count(1.)))
+        );
+        for (typed, action) in [
+            ("code", Action::Code),
+            ("upgrade", Action::Upgrade),
+            ("undo", Action::Undo),
+            ("pause", Action::Pause),
+            ("Quit", Action::Quit),
+        ] {
+            assert_eq!(parse_action(typed).unwrap(), action);
+            // ces verbes ne prennent rien après eux
+            assert!(matches!(
+                parse_action(&format!("{typed} now")),
+                Err(ClidleError::UnknownCommand(_))
+            ));
+        }
+        for typed in ["", "dance", "buyer dev", "codes"] {
+            assert!(
+                matches!(parse_action(typed), Err(ClidleError::UnknownCommand(_))),
+                "{typed:?}"
+            );
+        }
+        assert!(matches!(parse_action("buy"), Err(ClidleError::MissingItem)));
+
+        assert_eq!(
+            parse_macro("buy dev 2; code;;").unwrap(),
+            [
+                Action::Trade(command(Verb::Buy, "dev", Quantity::Count(2.))),
+                Action::Code
+            ]
+        );
+        assert!(matches!(
+            parse_macro("code; dance"),
+            Err(ClidleError::MacroStepFailed(2, error)) if matches!(*error, ClidleError::UnknownCommand(_))
+        ));
+        assert!(matches!(
+            parse_macro("dance"),
+            Err(ClidleError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn trades_are_parsed_in_every_form() {
+        for (typed, item, quantity) in [
+            ("dev", "dev", Quantity::Count(1.)),
+            ("dev 10", "dev", Quantity::Count(10.)),
+            ("  dev   10  ", "dev", Quantity::Count(10.)),
+            ("dev 2.5", "dev", Quantity::Count(2.5)),
+            ("dev next", "dev", Quantity::NextMilestone),
+            ("dev NEXT", "dev", Quantity::NextMilestone),
+            ("dev all", "dev", Quantity::Max),
+            ("dev max", "dev", Quantity::Max),
+            (
+                "version control system",
+                "version control system",
+                Quantity::Count(1.),
+            ),
+            (
+                "version control system 3",
+                "version control system",
+                Quantity::Count(3.),
+            ),
+        ] {
+            assert_eq!(
+                parse_trade(Verb::Buy, typed).unwrap(),
+                command(Verb::Buy, item, quantity),
+                "{typed:?}"
+            );
+        }
+        assert_eq!(
+            parse_command("sell git all").unwrap(),
+            command(Verb::Sell, "git", Quantity::Max)
+        );
+        for typed in ["dev 0", "dev -2", "dev 1e999", "dev NaN", "dev 3x", "dev -"] {
+            assert!(
+                matches!(
+                    parse_trade(Verb::Buy, typed),
+                    Err(ClidleError::InvalidQuantity(_))
+                ),
+                "{typed:?}"
+            );
+        }
+        for typed in ["", "   "] {
+            assert!(matches!(
+                parse_trade(Verb::Sell, typed),
+                Err(ClidleError::MissingItem)
+            ));
+        }
+        // un mot seul est toujours le nom de l'item, la recherche dira s'il n'existe pas
+        assert_eq!(
+            parse_trade(Verb::Sell, "all").unwrap(),
+            command(Verb::Sell, "all", Quantity::Count(1.))
+        );
+        assert!(matches!(
+            parse_command("trade dev"),
+            Err(ClidleError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn selling_all_of_nothing_is_an_error() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 3);
+        let app = game(config);
+        let sell_all = |item| command(Verb::Sell, item, Quantity::Max);
+        assert_eq!(command_count(&app, &sell_all("git")).unwrap(), 3.);
+        assert!(matches!(
+            command_count(&app, &sell_all("dev")),
+            Err(ClidleError::SellingItemNotOwned(item, _, _)) if item == "dev"
+        ));
+        // un item inconnu est signalé par la vente elle-même
+        assert_eq!(command_count(&app, &sell_all("nope")).unwrap(), 0.);
+        assert!(matches!(
+            command_count(&app, &command(Verb::Sell, "git", Quantity::NextMilestone)),
+            Err(ClidleError::InvalidQuantity(_))
+        ));
+        assert!(matches!(
+            command_count(&app, &command(Verb::Buy, "git", Quantity::Count(1.5))),
+            Err(ClidleError::InvalidQuantity(_))
+        ));
+    }
+
+    #[test]
+    fn only_sharp_changes_flash() {
+        // un quart du plus grand des deux, et au moins dix lignes
+        assert_eq!(flash_for(Lines(100.), Lines(133.)), None);
+        assert_eq!(flash_for(Lines(100.), Lines(134.)), Some(Flash::Gain));
+        assert_eq!(flash_for(Lines(100.), Lines(75.)), Some(Flash::Loss));
+        assert_eq!(flash_for(Lines(100.), Lines(76.)), None);
+        assert_eq!(flash_for(Lines(0.), Lines(9.)), None);
+        assert_eq!(flash_for(Lines(0.), Lines(10.)), Some(Flash::Gain));
+        assert_eq!(flash_for(Lines(12.), Lines(0.)), Some(Flash::Loss));
+        assert_eq!(flash_for(Lines(50.), Lines(50.)), None);
+        assert_eq!(flash_for(Lines(1e12), Lines(1e12 + 1e9)), None);
+    }
+
+    #[test]
+    fn prestige_commits_and_upgrades() {
+        let million = 1_000_000;
+        assert_eq!(prestige_commits(Lines::ZERO, million), 0);
+        assert_eq!(prestige_commits(Lines(999_999.), million), 0);
+        assert_eq!(prestige_commits(Lines(1e6), million), 1);
+        // quatre fois plus de lignes pour deux fois plus de commits
+        assert_eq!(prestige_commits(Lines(4e6), million), 2);
+        assert_eq!(prestige_commits(Lines(8.9e6), million), 2);
+        assert_eq!(prestige_commits(Lines(9e6), million), 3);
+        assert_eq!(prestige_commits(Lines(-1.), million), 0);
+        assert_eq!(prestige_commits(Lines(f64::NAN), million), 0);
+        assert_eq!(prestige_commits(Lines(4.), 0), 2);
+
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        assert!(matches!(
+            app.prestige(),
+            Err(ClidleError::NothingToPrestige(1_000_000))
+        ));
+        app.lifetime_lines = Lines(36e6);
+        app.prestige().unwrap();
+        assert_eq!(app.commits, 6);
+        assert_eq!(app.lifetime_lines, Lines::ZERO);
+        assert_eq!(app.total_cps(), 1.);
+        assert!(matches!(
+            buy_upgrade(&mut app, "nope"),
+            Err(ClidleError::UpgradeNotKnown(_))
+        ));
+        buy_upgrade(&mut app, "tests").unwrap();
+        assert_eq!(app.commits, 5);
+        assert_eq!(app.total_cps(), 1.5);
+        assert!(matches!(
+            buy_upgrade(&mut app, "tests"),
+            Err(ClidleError::UpgradeAlreadyBought(_))
+        ));
+        buy_upgrade(&mut app, "ci").unwrap();
+        assert_eq!(app.commits, 0);
+        assert_eq!(app.total_cps(), 3.);
+        let next = &app.config.prestige_upgrades[2];
+        let (name, cost) = (next.name.clone(), next.cost);
+        assert!(matches!(
+            buy_upgrade(&mut app, &name),
+            Err(ClidleError::NotEnoughCommits(_, needed, 0)) if needed == cost
+        ));
+    }
+
+    #[test]
+    fn purchases_keep_the_reserve() {
+        let mut app = game(Config {
+            min_reserve: 20,
+            starting_lines: Lines(30.),
+            ..Config::default()
+        });
+        // il reste tout juste la réserve
+        buy_item(&mut app, "git".to_string()).unwrap();
+        assert_eq!(app.code_lines, Lines(20.));
+        assert!(matches!(
+            buy_item(&mut app, "dev".to_string()),
+            Err(ClidleError::ReserveBreached(item, 5, 20)) if item == "dev"
+        ));
+        assert_eq!(app.code_lines, Lines(20.));
+        assert_eq!(app.owned_count(ItemId(0)), 0.);
+        app.code_lines = Lines(24.9);
+        assert!(matches!(
+            buy_item(&mut app, "dev".to_string()),
+            Err(ClidleError::ReserveBreached(..))
+        ));
+        app.code_lines = Lines(25.);
+        buy_item(&mut app, "dev".to_string()).unwrap();
+        assert_eq!(app.code_lines, Lines(20.));
+    }
+
+    #[test]
+    fn repeated_errors_are_counted_in_one_entry() {
+        let mut app = game(Config::default());
+        for _ in 0..3 {
+            play(&mut app, GameState::BuyItem("f".to_string()));
+        }
+        assert_eq!(app.error_repeats, 3);
+        let error = app.error.as_ref().unwrap_err().localized(app.lang);
+        assert_eq!(
+            error_line(&app).unwrap(),
+            tf(Msg::RepeatedErrorLine, app.lang, &[&error, &3])
+        );
+        let lines = screen(&mut app, 100, 30);
+        assert_eq!(lines.iter().filter(|row| row.contains(&error)).count(), 1);
+        // une autre erreur repart de un
+        play(&mut app, GameState::BuyItem("nope".to_string()));
+        assert_eq!(app.error_repeats, 1);
+        assert!(error_line(&app)
+            .unwrap()
+            .starts_with(&tf(Msg::ErrorLine, app.lang, &[&""])));
+        app.report(Ok(()));
+        play(&mut app, GameState::BuyItem("nope".to_string()));
+        assert_eq!(app.error_repeats, 1);
+    }
+
+    #[test]
+    fn exotic_keys_change_nothing() {
+        let exotic = [
+            KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Insert, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Delete, KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::Null, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::Left, KeyModifiers::ALT),
+            KeyEvent::new(KeyCode::Char('é'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('😀'), KeyModifiers::NONE),
+            KeyEvent::new(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT,
+            ),
+            KeyEvent::new(KeyCode::Char('\u{0}'), KeyModifiers::NONE),
+        ];
+        let mut config = Config {
+            starting_lines: Lines(50.),
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 1);
+        let mut app = game(config);
+        let before = app.snapshot();
+        for key in exotic {
+            let state = handle_key(&mut app, key);
+            assert!(play(&mut app, state), "{key:?}");
+            assert!(matches!(app.input_mode, InputMode::Normal), "{key:?}");
+        }
+        assert_eq!(app.snapshot(), before);
+        assert!(app.error.is_ok());
+        assert!(app.input.is_empty());
+        // avec --debug elles sont signalées, sans rien changer non plus
+        app.debug = Some(DebugInfo::default());
+        let logged = app.log.len();
+        for key in exotic {
+            let state = handle_key(&mut app, key);
+            assert!(play(&mut app, state), "{key:?}");
+        }
+        assert_eq!(app.snapshot(), before);
+        assert!(app.log.len() > logged);
+    }
+
+    #[test]
+    fn auto_code_writes_a_click_every_second() {
+        let mut config = Config {
+            auto_code: true,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(1. + 1.));
+        app.update(5);
+        assert_eq!(app.code_lines, Lines(12.));
+        // un niveau de code de plus écrit plus à chaque seconde
+        app.code_lines = Lines(101.);
+        upgrade_click(&mut app).unwrap();
+        assert_eq!(app.click_power(), 2.);
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(1. + 2. + 1.));
+        // la touche l'arrête
+        type_keys(&mut app, "a");
+        assert!(!app.auto_code);
+        app.update(1);
+        assert_eq!(app.code_lines, Lines(5.));
+    }
+
+    #[test]
+    fn the_item_table_has_a_row_per_item() {
+        let app = game(Config::default());
+        let table = items_table(&app.items_index);
+        let rows: Vec<&str> = table.lines().collect();
+        assert_eq!(rows.len(), 1 + app.items_index.len());
+        assert_eq!(
+            rows[0].split_whitespace().collect::<Vec<_>>(),
+            ["id", "name", "long_name", "cost", "cps"]
+        );
+        assert_eq!(
+            rows[3].split_whitespace().collect::<Vec<_>>(),
+            ["2", "git", "version", "control", "system", "10", "0.5"]
+        );
+        // les colonnes sont alignées
+        let cost_column = rows[0].find("cost").unwrap();
+        assert!(rows[1..]
+            .iter()
+            .all(|row| row[cost_column..].starts_with(|c: char| c.is_ascii_digit())));
+        assert_eq!(items_table(&[]), "id  name  long_name  cost  cps\n");
+    }
+
+    #[test]
+    fn bulk_buys_are_cut_down_to_the_budget() {
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            budget_fraction: Some(0.5),
+            ..Config::default()
+        });
+        // 50 lignes paient 5 git sur les 8 demandés
+        assert_eq!(within_budget(&mut app, "git", 8.).unwrap(), 5.);
+        assert_eq!(
+            app.log.last().unwrap(),
+            &tf(Msg::BudgetTruncated, app.lang, &[&5, &"8", &"git", &50])
+        );
+        let logged = app.log.len();
+        assert_eq!(within_budget(&mut app, "git", 4.).unwrap(), 4.);
+        assert_eq!(app.log.len(), logged);
+        assert!(matches!(
+            within_budget(&mut app, "f", 1.),
+            Err(ClidleError::OverBudget(item, 3000, 50)) if item == "f"
+        ));
+        // sans budget tout est demandé
+        app.config.budget_fraction = None;
+        assert_eq!(within_budget(&mut app, "git", 8.).unwrap(), 8.);
+    }
+
+    #[test]
+    fn the_summary_shows_the_session_only() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        // ce qui vient des parties d'avant ne compte pas
+        app.lifetime_lines = Lines(5000.);
+        app.update(10);
+        app.produce(Lines(app.click_power()));
+        let mut terminal = Terminal::new(tui::backend::TestBackend::new(60, 14)).unwrap();
+        let mut input = typing("x");
+        show_summary(&mut terminal, &mut input, &app).unwrap();
+        assert!(input.0.is_empty());
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer
+            .content
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        let produced = format_large(11., app.config.decimal_places);
+        assert!(
+            text.contains(&tf(Msg::SummaryLines, app.lang, &[&produced])),
+            "{text}"
+        );
+        assert!(!text.contains('5'), "{text}");
+        // seule la touche pour quitter le montre, pas Ctrl-C
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(matches!(handle_key(&mut app, ctrl_c), GameState::Interrupt));
+        let quit = press(KeyCode::Char(app.keys.quit));
+        assert!(matches!(handle_key(&mut app, quit), GameState::Quit));
+    }
+
+    #[test]
+    fn bulk_discounts_apply_from_their_tier() {
+        let mut app = game(Config::default());
+        app.config.bulk_discounts = vec![
+            BulkDiscount {
+                min_count: 10,
+                percent: 20.,
+            },
+            BulkDiscount {
+                min_count: 100,
+                percent: 90.,
+            },
+        ];
+        assert_eq!(bulk_discount(&app, 9.), None);
+        assert_eq!(bulk_price(&app, 10, 9.), price_for(10, 9.));
+        assert_eq!(bulk_discount(&app, 10.), Some(20.));
+        assert_eq!(
+            bulk_price(&app, 10, 10.),
+            (price_for(10, 10.) as f64 * 0.8).ceil() as u64
+        );
+        // jamais au point qu'acheter puis revendre rapporte
+        assert_eq!(bulk_discount(&app, 100.), Some(MAX_BULK_DISCOUNT));
+        assert!(bulk_price(&app, 10, 100.) > price_for(10, 100.) / 2);
+    }
+
+    #[test]
+    fn fast_forward_credits_ten_times_the_seconds() {
+        let mut config = Config::default();
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        app.debug = Some(DebugInfo::default());
+        let clock = manual_clock(&mut app);
+        let mut last_tick = clock.now();
+        handle_key(&mut app, press(KeyCode::F(10)));
+        assert_eq!(app.time_scale, FAST_FORWARD);
+        clock.advance(Duration::from_secs(1));
+        assert!(tick(&mut app, &mut last_tick));
+        assert_eq!(app.code_lines, Lines(FAST_FORWARD));
+        assert_eq!(app.played_secs, 10);
+        // et de nouveau à vitesse normale
+        handle_key(&mut app, press(KeyCode::F(10)));
+        assert_eq!(app.time_scale, 1.);
+        clock.advance(Duration::from_secs(1));
+        assert!(tick(&mut app, &mut last_tick));
+        assert_eq!(app.code_lines, Lines(FAST_FORWARD + 1.));
+    }
+
+    #[test]
+    fn saves_keep_only_keys_other_than_the_config() {
+        let mut config = Config::default();
+        config.keys.code = 'e';
+        let mut app = game(config);
+        assert_eq!(app.snapshot().keys, None);
+        // une sauvegarde partagée emporte ses touches
+        let mut shared = app.snapshot();
+        let mut keys = app.config.keys.clone();
+        keys.code = 'z';
+        shared.keys = Some(keys.clone());
+        app.restore(shared);
+        assert_eq!(app.keys, keys);
+        assert_eq!(app.snapshot().keys, Some(keys));
+        // des touches en conflit laissent celles de la configuration
+        let mut app = game(app.config.clone());
+        let mut conflicting = app.snapshot();
+        let mut keys = app.config.keys.clone();
+        keys.buy = keys.code;
+        conflicting.keys = Some(keys);
+        let logged = app.log.len();
+        app.restore(conflicting);
+        assert_eq!(app.keys, app.config.keys);
+        assert_eq!(app.log.len(), logged + 1);
+        assert_eq!(app.snapshot().keys, None);
+    }
+
+    #[test]
+    fn contributions_are_tiered_by_share() {
+        assert_eq!(contribution(1., 0.), None);
+        assert_eq!(contribution(1., -2.), None);
+        assert_eq!(contribution(1., f64::INFINITY), None);
+        assert_eq!(contribution(1., f64::NAN), None);
+        assert_eq!(contribution(25., 100.), Some(Contribution::Major));
+        assert_eq!(contribution(100., 100.), Some(Contribution::Major));
+        assert_eq!(contribution(24.9, 100.), Some(Contribution::Minor));
+        assert_eq!(contribution(5., 100.), Some(Contribution::Minor));
+        assert_eq!(contribution(4.9, 100.), Some(Contribution::Negligible));
+        // un item qui coûte des lignes ne pèse rien
+        assert_eq!(contribution(-50., 100.), Some(Contribution::Negligible));
+    }
+
+    #[test]
+    fn only_selling_the_last_copy_asks_first() {
+        let mut config = Config {
+            confirm_last_sell: true,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        // 2 → 1 se vend sans question
+        sell_checked(&mut app, "git", 1.).unwrap();
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        // 1 → 0 attend la confirmation
+        sell_checked(&mut app, "git", 1.).unwrap();
+        assert!(matches!(app.input_mode, InputMode::ConfirmSell { .. }));
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        type_keys(&mut app, "n");
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        sell_checked(&mut app, "git", 1.).unwrap();
+        type_keys(&mut app, "y");
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(app.owned_count(ItemId(2)), 0.);
+    }
+
+    #[test]
+    fn the_outlook_follows_the_production() {
+        assert_eq!(outlook(Lines(10.), -2.), Outlook::Doomed(5.));
+        assert_eq!(outlook(Lines(-3.), -2.), Outlook::Doomed(0.));
+        assert_eq!(outlook(Lines(10.), 0.), Outlook::Stable);
+        assert_eq!(outlook(Lines(10.), f64::NAN), Outlook::Stable);
+        assert_eq!(outlook(Lines(10.), 0.5), Outlook::Growing);
+        // le journal ne le dit qu'au changement, pas à chaque seconde
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            ..Config::default()
+        });
+        app.update(1);
+        let logged = app.log.len();
+        play(&mut app, GameState::BuyItem("git".to_string()));
+        let growing = t(Msg::LinesGrowing, app.lang);
+        assert_eq!(app.log.last().unwrap(), growing);
+        for _ in 0..3 {
+            app.update(1);
+        }
+        let log = &app.log[logged..];
+        assert_eq!(log.iter().filter(|entry| *entry == growing).count(), 1);
+        play(&mut app, GameState::SellItem("git".to_string()));
+        assert_eq!(app.log.last().unwrap(), t(Msg::LinesStable, app.lang));
+    }
+
+    #[test]
+    fn the_game_fits_from_the_minimum_size() {
+        let size = |width, height| Rect::new(0, 0, width, height);
+        assert!(fits(size(80, 24), 80, 24));
+        assert!(fits(size(200, 60), 80, 24));
+        assert!(!fits(size(79, 24), 80, 24));
+        assert!(!fits(size(80, 23), 80, 24));
+        assert!(!fits(size(0, 0), 1, 1));
+        assert!(fits(size(0, 0), 0, 0));
+    }
+
+    #[test]
+    fn csv_names_with_commas_are_quoted() {
+        let items = r#"[
+            {"cps": 1, "cost": 10, "name": "dev, senior", "long_name": "senior developer"},
+            {"cps": 2, "cost": 20, "name": "git", "long_name": "version control system"},
+            {"cps": 3, "cost": 30, "name": "the \"ci\"", "long_name": "integration"}
+        ]"#;
+        let mut config = Config::default();
+        config.starting_items.insert("dev, senior".to_string(), 2);
+        config.starting_items.insert("the \"ci\"".to_string(), 1);
+        let app = game_of(items, config);
+        assert_eq!(
+            owned_csv(&app),
+            "name,count,cps_each,total_cps,value\n\
+             \"dev, senior\",2,1,2,10\n\
+             \"the \"\"ci\"\"\",1,3,3,15\n"
+        );
+        assert_eq!(csv_field("git"), "git");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn the_session_runs_on_the_game_clock() {
+        let mut config = Config {
+            idle_quit_secs: Some(5),
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 2);
+        let mut app = game(config);
+        let clock = manual_clock(&mut app);
+        let mut last_tick = clock.now();
+        for _ in 0..4 {
+            clock.advance(Duration::from_secs(1));
+            assert!(tick(&mut app, &mut last_tick));
+        }
+        assert_eq!(app.code_lines, Lines(4.));
+        assert_eq!(app.stats.duration(clock.now()), Duration::from_secs(4));
+        assert_eq!(app.gains.rate(clock.now()), Some(1.));
+        assert!(!idle_expired(
+            app.last_activity,
+            clock.now(),
+            app.config.idle_quit_secs
+        ));
+        clock.advance(Duration::from_secs(1));
+        assert!(tick(&mut app, &mut last_tick));
+        assert!(idle_expired(
+            app.last_activity,
+            clock.now(),
+            app.config.idle_quit_secs
+        ));
+        // une touche relance l'attente
+        handle_input(&mut app, &mut typing("c")).unwrap();
+        assert_eq!(app.last_activity, clock.now());
+    }
+
+    #[test]
+    fn macros_stop_at_a_failing_or_unconfirmed_step() {
+        let mut app = game(Config {
+            starting_lines: Lines(100.),
+            confirm_above: Some(20),
+            ..Config::default()
+        });
+        let macro_of = |typed| GameState::Macro(parse_macro(typed).unwrap());
+        play(&mut app, macro_of("buy git; buy f; code"));
+        assert!(matches!(
+            &app.error,
+            Err(ClidleError::MacroStepFailed(2, error))
+                if matches!(**error, ClidleError::NotEnoughLines(..))
+        ));
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        assert_eq!(app.code_lines, Lines(90.));
+        // une étape qui demande confirmation arrête la macro et garde la question
+        play(&mut app, macro_of("buy git 3; code"));
+        assert!(matches!(
+            app.error,
+            Err(ClidleError::MacroStepUnconfirmed(1))
+        ));
+        assert!(matches!(app.input_mode, InputMode::ConfirmBuy { .. }));
+        assert_eq!(app.code_lines, Lines(90.));
+        type_keys(&mut app, "y");
+        assert_eq!(app.owned_count(ItemId(2)), 4.);
+        // en dernière étape la question reste ouverte sans erreur
+        play(&mut app, macro_of("code; buy git 3"));
+        assert!(app.error.is_ok());
+        assert!(matches!(app.input_mode, InputMode::ConfirmBuy { .. }));
+    }
+
+    #[test]
+    fn only_a_real_suspend_is_credited_at_the_offline_rate() {
+        let mut config = Config {
+            offline_rate: 0.25,
+            ..Config::default()
+        };
+        config.starting_items.insert("git".to_string(), 4);
+        let mut app = game(config);
+        let cps = app.total_cps();
+        let clock = manual_clock(&mut app);
+        let mut last_tick = clock.now();
+        // quelques secondes de retard sont créditées en entier, sans message
+        clock.advance(Duration::from_secs(SUSPEND_SECS));
+        // la production qui démarre est déjà dans le journal
+        app.track_outlook();
+        let logged = app.log.len();
+        assert!(tick(&mut app, &mut last_tick));
+        assert_eq!(app.code_lines, Lines(SUSPEND_SECS as f64 * cps));
+        assert_eq!(app.log.len(), logged);
+        // au-delà, le temps d'absence ne compte qu'au taux hors ligne
+        let before = app.code_lines;
+        let away = 400;
+        clock.advance(Duration::from_secs(away));
+        assert!(tick(&mut app, &mut last_tick));
+        assert_eq!(
+            app.code_lines - before,
+            Lines(away as f64 * cps * app.config.offline_rate)
+        );
+        assert!(app.log[logged..].contains(&tf(Msg::CaughtUp, app.lang, &[&away, &100])));
+    }
+
+    #[test]
+    fn the_counter_rolls_to_the_real_lines() {
+        for (from, to) in [(0., 1000.), (1000., 0.), (5., 1e12)] {
+            let (mut shown, target) = (Lines(from), Lines(to));
+            let mut frames = 0;
+            while shown != target {
+                let next = approach(shown, target);
+                // toujours plus près, sans jamais dépasser
+                assert!((target.0 - next.0).abs() < (target.0 - shown.0).abs());
+                assert!((next.0 - from) * (to - from) >= 0.);
+                shown = next;
+                frames += 1;
+                assert!(frames < 200, "{from} → {to}");
+            }
+        }
+        assert_eq!(approach(Lines(10.), Lines(10.5)), Lines(10.5));
+        assert_eq!(approach(Lines(0.), Lines(100.)), Lines(100. * SMOOTHING));
+        assert_eq!(
+            approach(Lines(0.), Lines(f64::MAX)),
+            Lines(f64::MAX * SMOOTHING)
+        );
+        assert_eq!(approach(Lines(-f64::MAX), Lines(f64::MAX)), Lines(f64::MAX));
+    }
+
+    #[test]
+    fn favorites_toggle_and_filter_the_list() {
+        let mut app = game(Config::default());
+        let listed = |app: &App| -> Vec<String> {
+            app.listed_items()
+                .iter()
+                .map(|item| item.name.clone())
+                .collect()
+        };
+        toggle_favorite(&mut app, " git ").unwrap();
+        toggle_favorite(&mut app, "dev").unwrap();
+        assert_eq!(app.favorites, HashSet::from([ItemId(0), ItemId(2)]));
+        assert_eq!(
+            app.log.last().unwrap(),
+            &tf(Msg::FavoriteAdded, app.lang, &[&"dev"])
+        );
+        assert_eq!(listed(&app), ["dev", "f", "git"]);
+        type_keys(&mut app, "M");
+        assert_eq!(listed(&app), ["dev", "git"]);
+        // le retirer le cache aussitôt
+        toggle_favorite(&mut app, "dev").unwrap();
+        assert_eq!(
+            app.log.last().unwrap(),
+            &tf(Msg::FavoriteRemoved, app.lang, &[&"dev"])
+        );
+        assert_eq!(listed(&app), ["git"]);
+        assert!(matches!(
+            toggle_favorite(&mut app, "gti"),
+            Err(ClidleError::BuyingItemNotKnown(..))
+        ));
+        type_keys(&mut app, "M");
+        assert_eq!(listed(&app), ["dev", "f", "git"]);
+    }
+
+    #[test]
+    fn every_change_is_saved_in_ironman() {
+        let dir = std::env::temp_dir().join(format!("clidle-{}-ironman", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SAVE_PATH);
+        let mut app = game(Config {
+            ironman: true,
+            starting_lines: Lines(20.),
+            ..Config::default()
+        });
+        app.autosave = Some(path.to_str().unwrap().to_string());
+        let saved = || Save::read(&path).unwrap().unwrap();
+        // coder ne passe pas par `play`, mais change la partie
+        type_keys(&mut app, "c");
+        autosave(&mut app);
+        assert_eq!(saved().code_lines, Lines(21.));
+        type_keys(&mut app, "bgit\n\x1b");
+        autosave(&mut app);
+        assert_eq!(saved().owned_items, HashMap::from([(ItemId(2), 1.)]));
+        type_keys(&mut app, "mdev\n");
+        autosave(&mut app);
+        assert_eq!(saved().favorites, ["dev"]);
+        // rien n'est réécrit quand rien n'a changé
+        fs::remove_file(&path).unwrap();
+        type_keys(&mut app, "M");
+        autosave(&mut app);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_spinner_stays_between_one_and_affordable() {
+        let mut app = game(Config {
+            starting_lines: Lines(30.),
+            ..Config::default()
+        });
+        type_keys(&mut app, "bgit\t");
+        assert!(matches!(&app.input_mode, InputMode::Spinner { item } if item == "git"));
+        assert_eq!(app.buy_quantity, 1);
+        // jamais sous un
+        type_keys(&mut app, "-");
+        assert_eq!(app.buy_quantity, 1);
+        type_keys(&mut app, "+");
+        assert_eq!(app.buy_quantity, 2);
+        // 30 lignes paient 3 git, pas plus
+        let max = max_affordable(&app, app.find_item("git").unwrap()) as u64;
+        for _ in 0..10 {
+            type_keys(&mut app, "+");
+        }
+        assert_eq!(app.buy_quantity, max);
+        type_keys(&mut app, "-");
+        assert_eq!(app.buy_quantity, max - 1);
+        type_keys(&mut app, "\n");
+        assert_eq!(app.owned_count(ItemId(2)), (max - 1) as f64);
+        assert!(matches!(app.input_mode, InputMode::Buy));
+        // un item hors de prix reste à un
+        app.buy_quantity = 1;
+        assert_eq!(spin(&app, "f", 1), 1);
+    }
+
+    #[test]
+    fn wrapped_lines_fit_the_width() {
+        assert_eq!(
+            wrap_text("buy the cheapest items first", 12),
+            ["buy the", "cheapest", "items first"]
+        );
+        assert_eq!(wrap_text("refactoring", 4), ["refa", "ctor", "ing"]);
+        assert_eq!(wrap_text("", 10), Vec::<String>::new());
+        assert_eq!(wrap_text("a b", 0), ["a", "b"]);
+        let text = "コードを書く developers write 代码 and ship features";
+        for width in 1..30 {
+            let lines = wrap_text(text, width);
+            // un caractère large ne se coupe pas, il déborde seul sur une ligne d'une colonne
+            assert!(
+                lines.iter().all(|line| line.width() <= width.max(2)),
+                "{width}: {lines:?}"
+            );
+            let joined: String = lines.concat().split_whitespace().collect();
+            let expected: String = text.split_whitespace().collect();
+            assert_eq!(joined, expected, "{width}");
+        }
+    }
+
+    #[test]
+    fn auto_prestige_fires_once_at_the_threshold() {
+        let mut app = game(Config {
+            prestige_lines: 100,
+            auto_prestige_at: Some(400.),
+            ..Config::default()
+        });
+        app.produce(Lines(399.));
+        auto_prestige(&mut app);
+        assert_eq!(app.commits, 0);
+        app.produce(Lines(1.));
+        auto_prestige(&mut app);
+        assert_eq!(app.commits, 2);
+        assert_eq!(app.code_lines, app.config.starting_lines);
+        let auto = tf(
+            Msg::AutoPrestiged,
+            app.lang,
+            &[&format_lines(400., app.config.decimal_places)],
+        );
+        assert_eq!(app.log.last().unwrap(), &auto);
+        // les lignes repartent de zéro, rien à refaire avant le seuil
+        auto_prestige(&mut app);
+        assert_eq!(app.commits, 2);
+        assert_eq!(app.log.iter().filter(|entry| **entry == auto).count(), 1);
+        assert!(app.error.is_ok());
+    }
+
+    #[test]
+    fn comparisons_gather_price_production_and_payback() {
+        let items = r#"[
+            {"cps": 0.5, "cost": 10, "name": "git", "long_name": "version control system"},
+            {"cps": 1, "cost": 0, "name": "intern", "long_name": "intern"},
+            {"cps": -1, "cost": 20, "name": "legacy", "long_name": "legacy code"}
+        ]"#;
+        let mut app = game_of(items, Config::default());
+        assert_eq!(
+            comparison(&app, ItemId(0)),
+            Comparison {
+                name: "git".to_string(),
+                price: 10,
+                cps: 0.5,
+                efficiency: Some(50.),
+                break_even: Some(20.),
+            }
+        );
+        assert_eq!(comparison(&app, ItemId(1)).efficiency, None);
+        assert_eq!(comparison(&app, ItemId(1)).break_even, Some(0.));
+        assert_eq!(comparison(&app, ItemId(2)).break_even, None);
+        // le troisième item remplace le plus ancien
+        for name in ["git", "intern", "legacy"] {
+            compare(&mut app, name).unwrap();
+        }
+        assert_eq!(app.compared, [Some(ItemId(1)), Some(ItemId(2))]);
+        assert!(matches!(
+            compare(&mut app, "gti"),
+            Err(ClidleError::BuyingItemNotKnown(..))
+        ));
+    }
+
+    #[test]
+    fn unlocking_spends_like_a_purchase() {
+        let items = r#"[
+            {"cps": 1, "cost": 10, "name": "ai", "long_name": "assistant", "unlock_cost": 50}
+        ]"#;
+        let mut app = game_of(
+            items,
+            Config {
+                starting_lines: Lines(49.),
+                ..Config::default()
+            },
+        );
+        assert!(matches!(
+            unlock(&mut app, "ai"),
+            Err(ClidleError::UnlockTooExpensive(item, 50, _)) if item == "ai"
+        ));
+        app.code_lines = Lines(70.);
+        unlock(&mut app, "ai").unwrap();
+        assert_eq!(app.code_lines, Lines(20.));
+        buy_item(&mut app, "ai".to_string()).unwrap();
+        assert_eq!(app.owned_count(ItemId(0)), 1.);
+        assert_eq!(app.code_lines, Lines(10.));
+        // la réserve et le budget comptent comme pour un achat
+        app.reset();
+        app.config.min_reserve = 30;
+        app.code_lines = Lines(70.);
+        assert!(matches!(
+            unlock(&mut app, "ai"),
+            Err(ClidleError::ReserveBreached(_, 50, 30))
+        ));
+        app.config.min_reserve = 0;
+        app.config.budget_fraction = Some(0.5);
+        assert!(matches!(
+            unlock(&mut app, "ai"),
+            Err(ClidleError::OverBudget(_, 50, 35))
+        ));
+        assert_eq!(app.code_lines, Lines(70.));
+        assert!(app.unlocked_items.is_empty());
+    }
+
+    #[test]
+    fn the_most_affordable_can_really_be_bought() {
+        for affordability in [Affordability::Floored, Affordability::Exact] {
+            let items = r#"[{"cps": 1, "cost": 49, "name": "dev", "long_name": "developer"}]"#;
+            let mut app = game_of(
+                items,
+                Config {
+                    starting_lines: Lines(49.9),
+                    affordability,
+                    ..Config::default()
+                },
+            );
+            // toujours au moins un, pour que l'achat dise pourquoi il échoue
+            assert_eq!(max_affordable(&app, &app.items_index[0]), 1.);
+            let bought = buy(&mut app, "dev", 1.);
+            assert_eq!(
+                bought.is_ok(),
+                matches!(affordability, Affordability::Exact),
+                "{affordability:?}"
+            );
+            // la réserve se compte sur les lignes avec leurs fractions
+            app.reset();
+            app.config.min_reserve = 10;
+            app.code_lines = Lines(59.5);
+            app.items_index[0].cost = 10;
+            let count = max_affordable(&app, &app.items_index[0]);
+            assert_eq!(count, 4., "{affordability:?}");
+            buy(&mut app, "dev", count).unwrap();
+            assert!(app.code_lines.0 >= 10.);
+        }
+    }
+
+    /// A terminal of `TestBackend` that also keeps what is written to it, like the bell.
+    struct Headless {
+        screen: tui::backend::TestBackend,
+        written: Vec<u8>,
+    }
+
+    impl Backend for Headless {
+        fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+        where
+            I: Iterator<Item = (u16, u16, &'a tui::buffer::Cell)>,
+        {
+            self.screen.draw(content)
+        }
+
+        fn hide_cursor(&mut self) -> io::Result<()> {
+            self.screen.hide_cursor()
+        }
+
+        fn show_cursor(&mut self) -> io::Result<()> {
+            self.screen.show_cursor()
+        }
+
+        fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+            self.screen.get_cursor()
+        }
+
+        fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+            self.screen.set_cursor(x, y)
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            self.screen.clear()
+        }
+
+        fn size(&self) -> io::Result<Rect> {
+            self.screen.size()
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Backend::flush(&mut self.screen)
+        }
+    }
+
+    impl io::Write for Headless {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn the_game_loop_runs_on_any_terminal() {
+        let mut app = game(Config {
+            starting_lines: Lines(20.),
+            session_summary: false,
+            bell: true,
+            thresholds: vec![25],
+            ..Config::default()
+        });
+        let mut terminal = Terminal::new(Headless {
+            screen: tui::backend::TestBackend::new(100, 30),
+            written: Vec::new(),
+        })
+        .unwrap();
+        // coder jusqu'au palier fait sonner, puis un achat et on quitte
+        let mut input = typing("cccccbgit\n\x1bq");
+        run_app(&mut terminal, &mut input, &mut app).unwrap();
+        assert!(input.0.is_empty());
+        assert_eq!(app.owned_count(ItemId(2)), 1.);
+        assert_eq!(app.code_lines, Lines(15.));
+        let backend = terminal.backend();
+        assert_eq!(backend.written, b"\x07");
+        let text: String = backend
+            .screen
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        // l'image d'avant la première touche
+        assert!(
+            text.contains(&format_lines(20., app.config.decimal_places)),
+            "{text}"
+        );
+        assert!(text.contains("version control system"), "{text}");
+    }
+
+    #[test]
+    fn alt_keys_are_replayed_as_alt_keys() {
+        let mut app = game(Config::default());
+        manual_clock(&mut app);
+        app.recording = Some(Recording::new(
+            app.config.clone(),
+            ITEMS.to_string(),
+            app.snapshot(),
+        ));
+        let alt_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT);
+        let mut input = Scripted([press(KeyCode::Char('c')), alt_c, alt_c].into());
+        while input.poll(Duration::ZERO).unwrap() {
+            let state = handle_input(&mut app, &mut input).unwrap();
+            assert!(play(&mut app, state));
+        }
+        // seule la frappe sans Alt a codé
+        assert_eq!(app.code_lines, Lines(1.));
+        let recording = app.recording.take().unwrap();
+        assert_eq!(recording.events[1].key_event(), Some(alt_c));
+        assert_eq!(
+            replay(&recording, "does/not/exist.json").unwrap(),
+            app.snapshot()
+        );
+        // les enregistrements d'avant gardent des touches sans Alt
+        let old: RecordedEvent =
+            serde_json::from_str(r#"{"key": {"key": "c", "ctrl": false}}"#).unwrap();
+        assert_eq!(old.key_event(), Some(press(KeyCode::Char('c'))));
+    }
 }
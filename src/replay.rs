@@ -0,0 +1,138 @@
+use std::{fs, io, path::Path};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, save::Save, ClidleError};
+
+/// One thing that happened during a recorded game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedEvent {
+    /// a second of production
+    Tick,
+    /// several seconds of production at once, after the game was suspended
+    CatchUp { secs: u64 },
+    /// a key press, named like `"c"`, `"enter"` or `"f12"`
+    Key {
+        key: String,
+        ctrl: bool,
+        /// absent from recordings made before Alt keys were told apart
+        #[serde(default)]
+        alt: bool,
+    },
+}
+
+/// A game recorded with `--record`, to play it again with `--replay`.
+///
+/// Production only depends on the ticks, so replaying the same events from
+/// the same state and config always ends in the same state.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub config: Config,
+    /// content of `items.json` when the recording started, none in older recordings
+    #[serde(default)]
+    pub catalog: Option<String>,
+    /// state when the recording started
+    pub start: Save,
+    pub events: Vec<RecordedEvent>,
+    /// state when the recording stopped, to check the replay against
+    #[serde(default)]
+    pub end: Option<Save>,
+}
+
+impl Recording {
+    pub fn new(config: Config, catalog: String, start: Save) -> Recording {
+        Recording {
+            config,
+            catalog: Some(catalog),
+            start,
+            events: Vec::new(),
+            end: None,
+        }
+    }
+
+    /// Remember a key press, unless it's a key the game never uses.
+    pub fn record_key(&mut self, key: &KeyEvent) {
+        if let Some(name) = key_name(key.code) {
+            self.events.push(RecordedEvent::Key {
+                key: name,
+                ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+                alt: key.modifiers.contains(KeyModifiers::ALT),
+            });
+        }
+    }
+
+    /// Read the recording at `path`.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::RecordingUnreadable` if it can't be read or parsed.
+    pub fn read(path: impl AsRef<Path>) -> Result<Recording, ClidleError> {
+        let path = path.as_ref();
+        let unreadable =
+            |reason: String| ClidleError::RecordingUnreadable(path.display().to_string(), reason);
+        let content = fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+        serde_json::from_str(&content).map_err(|e| unreadable(e.to_string()))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+impl RecordedEvent {
+    /// The key press to feed back to the game, none for a tick or an unknown key.
+    pub fn key_event(&self) -> Option<KeyEvent> {
+        match self {
+            RecordedEvent::Tick | RecordedEvent::CatchUp { .. } => None,
+            RecordedEvent::Key { key, ctrl, alt } => {
+                let mut modifiers = KeyModifiers::NONE;
+                modifiers.set(KeyModifiers::CONTROL, *ctrl);
+                modifiers.set(KeyModifiers::ALT, *alt);
+                Some(KeyEvent::new(parse_key(key)?, modifiers))
+            }
+        }
+    }
+}
+
+/// The name of a key in a recording, none for keys the game never uses.
+pub fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// The key `key_name` named `name`.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    match name {
+        "enter" => Some(KeyCode::Enter),
+        "esc" => Some(KeyCode::Esc),
+        "backspace" => Some(KeyCode::Backspace),
+        "tab" => Some(KeyCode::Tab),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "pageup" => Some(KeyCode::PageUp),
+        "pagedown" => Some(KeyCode::PageDown),
+        "home" => Some(KeyCode::Home),
+        "end" => Some(KeyCode::End),
+        _ => name.strip_prefix('f')?.parse().ok().map(KeyCode::F),
+    }
+}
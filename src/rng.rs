@@ -0,0 +1,72 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small random number generator, splitmix64.
+///
+/// Not fit for anything secret, but the same seed always gives the same numbers,
+/// which keeps recorded games replayable.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// A seed that changes from one launch to the next.
+    pub fn clock_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number between 0 included and 1 excluded.
+    pub fn next_f64(&mut self) -> f64 {
+        // les 53 bits de poids fort remplissent exactement la mantisse
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A factor between `1 - spread` and `1 + spread`, 1 on average.
+    pub fn jitter(&mut self, spread: f64) -> f64 {
+        1. + spread * (2. * self.next_f64() - 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_stays_in_its_spread_and_averages_one() {
+        let mut rng = Rng::new(42);
+        let draws = 100_000;
+        let mut sum = 0.;
+        for _ in 0..draws {
+            let factor = rng.jitter(0.3);
+            assert!((0.7..=1.3).contains(&factor), "{factor}");
+            sum += factor;
+        }
+        assert!((sum / draws as f64 - 1.).abs() < 0.005);
+        assert_eq!(rng.jitter(0.), 1.);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let mut c = Rng::new(8);
+        let drawn: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(drawn, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(drawn, (0..5).map(|_| c.next_u64()).collect::<Vec<_>>());
+        assert!((0..1000).all(|_| (0. ..1.).contains(&a.next_f64())));
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime},
+};
+
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+
+use crate::{base64, keys::KeyBindings, lines::Lines, ClidleError, ItemId};
+
+/// Where the game is saved, next to `items.json`.
+pub const SAVE_PATH: &str = "save.json";
+
+/// What is kept from one game to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Save {
+    pub code_lines: Lines,
+    /// (item_id, item count), fractional with `fractional_items`
+    #[serde(serialize_with = "serialize_counts")]
+    pub owned_items: HashMap<ItemId, f64>,
+    #[serde(default)]
+    pub lifetime_purchases: u64,
+    /// highest production per second reached
+    #[serde(default)]
+    pub peak_cps: f64,
+    /// code lines produced since the start
+    #[serde(default)]
+    pub lifetime_lines: Lines,
+    /// seconds of production since the start
+    #[serde(default)]
+    pub played_secs: u64,
+    /// click tiers bought
+    #[serde(default)]
+    pub click_tier: usize,
+    /// the items of `items.json` when saving, in id order, to notice it changed since
+    #[serde(default)]
+    pub catalog: Vec<SavedItem>,
+    /// prestige currency left to spend
+    #[serde(default)]
+    pub commits: u64,
+    /// names of the prestige upgrades bought
+    #[serde(default)]
+    pub prestige_upgrades: Vec<String>,
+    /// the keys played with, the config ones if none
+    #[serde(default)]
+    pub keys: Option<KeyBindings>,
+    /// names of the favorite items
+    #[serde(default)]
+    pub favorites: Vec<String>,
+    /// names of the items whose `unlock_cost` was paid
+    #[serde(default)]
+    pub unlocked_items: Vec<String>,
+}
+
+/// What a save remembers of an item, enough to find it again if its id changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedItem {
+    pub name: String,
+    pub cost: u64,
+}
+
+impl Save {
+    /// Read the save at `path`, `None` if there is no save yet.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::SaveMalformed` if the file exists but can't be read or parsed.
+    pub fn read(path: impl AsRef<Path>) -> Result<Option<Save>, ClidleError> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| ClidleError::SaveMalformed(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ClidleError::SaveMalformed(e.to_string())),
+        }
+    }
+
+    /// The save as a text to copy somewhere, `Save::import` reads it back.
+    pub fn export(&self) -> serde_json::Result<String> {
+        Ok(base64::encode(&serde_json::to_vec(self)?))
+    }
+
+    /// Read a save exported with `Save::export`.
+    ///
+    /// ## Errors
+    ///
+    /// `ClidleError::ImportInvalid` if `code` isn't an exported save.
+    pub fn import(code: &str) -> Result<Save, ClidleError> {
+        let json = base64::decode(code)
+            .ok_or_else(|| ClidleError::ImportInvalid("not base64".to_string()))?;
+        serde_json::from_slice(&json).map_err(|e| ClidleError::ImportInvalid(e.to_string()))
+    }
+
+    /// Write the save to `path`.
+    ///
+    /// The save is first written next to it in a temporary file, which then
+    /// replaces `path`. If anything goes wrong the previous save is left untouched.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = tmp_path(path);
+        let res = write_synced(&tmp, &serde_json::to_vec_pretty(self)?)
+            .and_then(|()| fs::rename(&tmp, path));
+        if res.is_err() {
+            // un fichier temporaire à moitié écrit ne sert à rien
+            let _ = fs::remove_file(&tmp);
+        }
+        res
+    }
+}
+
+/// A lock older than this was surely left by a crashed game, whatever its process.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where there is no telling whether the process of a lock still runs, a lock
+/// older than this is taken over, a game is rarely played that long in one go.
+const UNCHECKED_LOCK_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Proof that this game is the only one using a save, released when dropped.
+///
+/// It is a file next to the save, `save.json.lock`, holding the process id of the game.
+#[derive(Debug)]
+pub struct SaveLock {
+    path: PathBuf,
+}
+
+impl SaveLock {
+    /// Take the lock of the save at `path`, `None` if another running game holds it.
+    ///
+    /// A lock whose process is gone, or that is too old, is taken over.
+    pub fn acquire(path: impl AsRef<Path>) -> io::Result<Option<SaveLock>> {
+        let path = suffixed(path.as_ref(), ".lock");
+        match create_lock(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if !is_stale(&path) {
+                    return Ok(None);
+                }
+                fs::remove_file(&path)?;
+                // un autre jeu a pu reprendre le verrou entre temps
+                match create_lock(&path) {
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(None),
+                    res => res?,
+                }
+            }
+            res => res?,
+        }
+        Ok(Some(SaveLock { path }))
+    }
+}
+
+impl Drop for SaveLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Create the lock at `path` with our process id, failing if it already exists.
+fn create_lock(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    write!(file, "{}", process::id())
+}
+
+/// Whether the lock at `path` was left by a game that no longer runs.
+fn is_stale(path: &Path) -> bool {
+    let age = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    let older_than = |max: Duration| age.is_some_and(|age| age > max);
+    // un verrou illisible ou vide vient d'un jeu interrompu en l'écrivant
+    let pid = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| content.trim().parse::<u32>().ok());
+    older_than(STALE_LOCK_AGE)
+        || pid.is_none_or(|pid| match is_running(pid) {
+            Some(running) => !running,
+            None => older_than(UNCHECKED_LOCK_AGE),
+        })
+}
+
+/// Whether the process `pid` still runs, none if there is no way to tell.
+fn is_running(pid: u32) -> Option<bool> {
+    let procs = Path::new("/proc");
+    if procs.is_dir() {
+        return Some(procs.join(pid.to_string()).exists());
+    }
+    // sans /proc (macOS, BSD), `kill -0` dit si le processus existe sans rien lui envoyer
+    if cfg!(unix) {
+        return process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(process::Stdio::null())
+            .status()
+            .ok()
+            .map(|status| status.success());
+    }
+    None
+}
+
+/// Write whole item counts as integers, like before fractional counts existed,
+/// so a save without fractions can still be read by older versions of the game.
+fn serialize_counts<S: Serializer>(
+    counts: &HashMap<ItemId, f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(counts.len()))?;
+    for (item_id, count) in counts {
+        if count.fract() == 0. && (0. ..=u64::MAX as f64).contains(count) {
+            map.serialize_entry(item_id, &(*count as u64))?;
+        } else {
+            map.serialize_entry(item_id, count)?;
+        }
+    }
+    map.end()
+}
+
+/// `save.json` becomes `save.json.tmp`.
+fn tmp_path(path: &Path) -> PathBuf {
+    suffixed(path, ".tmp")
+}
+
+/// `path` with `suffix` added after its extension.
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut suffixed = path.as_os_str().to_owned();
+    suffixed.push(suffix);
+    suffixed.into()
+}
+
+/// Write `content` to `path` and wait for it to really be on the disk.
+fn write_synced(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for the test `name`.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clidle-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn save(code_lines: f64) -> Save {
+        serde_json::from_str(&format!(
+            r#"{{"code_lines": {code_lines}, "owned_items": {{"0": 2}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn write_replaces_the_save_through_a_temporary_file() {
+        let path = scratch("write").join(SAVE_PATH);
+        save(1.).write(&path).unwrap();
+        save(2.5).write(&path).unwrap();
+        assert_eq!(Save::read(&path).unwrap(), Some(save(2.5)));
+        assert!(!tmp_path(&path).exists());
+    }
+
+    #[test]
+    fn failed_write_keeps_the_previous_save() {
+        let path = scratch("failed").join(SAVE_PATH);
+        save(1.).write(&path).unwrap();
+        // le fichier temporaire ne peut pas être créé à la place d'un dossier
+        fs::create_dir(tmp_path(&path)).unwrap();
+        assert!(save(2.).write(&path).is_err());
+        assert_eq!(Save::read(&path).unwrap(), Some(save(1.)));
+    }
+
+    #[test]
+    fn the_lock_is_held_until_dropped() {
+        let path = scratch("lock").join(SAVE_PATH);
+        let lock = SaveLock::acquire(&path).unwrap();
+        assert!(lock.is_some());
+        let lock_path = suffixed(&path, ".lock");
+        assert_eq!(
+            fs::read_to_string(&lock_path).unwrap(),
+            process::id().to_string()
+        );
+        // ce jeu tourne toujours
+        assert!(SaveLock::acquire(&path).unwrap().is_none());
+        drop(lock);
+        assert!(!lock_path.exists());
+        let lock = SaveLock::acquire(&path).unwrap();
+        assert!(lock.is_some());
+        drop(lock);
+        // le verrou d'un jeu disparu, ou à moitié écrit, est repris
+        for left in [u32::MAX.to_string(), String::new()] {
+            fs::write(&lock_path, left).unwrap();
+            assert!(SaveLock::acquire(&path).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn item_ids_round_trip_as_numeric_keys() {
+        let mut saved = save(3.);
+        saved.owned_items = HashMap::from([(ItemId(0), 2.), (ItemId(12), 0.5)]);
+        let json = serde_json::to_string(&saved).unwrap();
+        assert!(
+            json.contains(r#""0":2"#) && json.contains(r#""12":0.5"#),
+            "{json}"
+        );
+        assert_eq!(serde_json::from_str::<Save>(&json).unwrap(), saved);
+        assert_eq!(serde_json::to_string(&ItemId(7)).unwrap(), "7");
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::lines::Lines;
+
+/// Counters about the current session, for players and balancers to review a run.
+///
+/// Unlike the game state, they start from zero each time the game is launched.
+#[derive(Debug)]
+pub struct SessionStats {
+    /// when the session started
+    pub started: Instant,
+    /// code lines produced, by coding or by items
+    pub lines_produced: Lines,
+    /// number of items bought
+    pub purchases: u64,
+    /// highest code lines per second reached
+    pub peak_cps: f64,
+    /// code lines thresholds celebrated
+    pub thresholds_reached: u64,
+}
+
+/// What is written by `--stats-out`.
+#[derive(Serialize)]
+struct StatsReport {
+    session_secs: f64,
+    lines_produced: Lines,
+    purchases: u64,
+    peak_cps: f64,
+    thresholds_reached: u64,
+}
+
+impl SessionStats {
+    /// Stats of a session `started` now, on the clock of the game.
+    pub fn new(started: Instant) -> SessionStats {
+        SessionStats {
+            started,
+            lines_produced: Lines::ZERO,
+            purchases: 0,
+            peak_cps: 0.,
+            thresholds_reached: 0,
+        }
+    }
+
+    /// How long the session lasted until `now`.
+    pub fn duration(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started)
+    }
+
+    /// Write the stats as json to `path`, the session ending `now`.
+    pub fn write(&self, path: impl AsRef<Path>, now: Instant) -> io::Result<()> {
+        let report = StatsReport {
+            session_secs: self.duration(now).as_secs_f64(),
+            lines_produced: self.lines_produced,
+            purchases: self.purchases,
+            peak_cps: self.peak_cps,
+            thresholds_reached: self.thresholds_reached,
+        };
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+    }
+}
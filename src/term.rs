@@ -0,0 +1,212 @@
+use std::{io, time::Duration};
+
+use crossterm::event::KeyEvent;
+use tui::Terminal;
+
+#[cfg(not(feature = "termion"))]
+pub use self::with_crossterm::{restore, setup, GameBackend};
+#[cfg(feature = "termion")]
+pub use self::with_termion::{restore, setup, GameBackend};
+
+/// Where the game reads the keys from, whatever the terminal library.
+///
+/// Keys are handed over as crossterm `KeyEvent`s, the ones the game and the
+/// recordings already understand, so only this reading part depends on the library.
+pub trait Input {
+    /// Whether something was typed, waiting at most `timeout`.
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// The next key press, waiting for it, none for anything else like a resize.
+    fn read(&mut self) -> io::Result<Option<KeyEvent>>;
+}
+
+/// The terminal set up by `setup`, and where its keys are read.
+pub type Session = (Terminal<GameBackend>, Box<dyn Input>);
+
+#[cfg(not(feature = "termion"))]
+mod with_crossterm {
+    use std::{
+        io::{self, Stdout},
+        time::Duration,
+    };
+
+    use crossterm::{
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEvent},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    };
+    use tui::{backend::CrosstermBackend, Terminal};
+
+    use super::{Input, Session};
+
+    pub type GameBackend = CrosstermBackend<Stdout>;
+
+    /// Keys read with crossterm.
+    struct CrosstermInput;
+
+    impl Input for CrosstermInput {
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            event::poll(timeout)
+        }
+
+        fn read(&mut self) -> io::Result<Option<KeyEvent>> {
+            match event::read()? {
+                Event::Key(key) => Ok(Some(key)),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    /// Take over the terminal: raw mode, alternate screen and mouse capture.
+    pub fn setup() -> io::Result<Session> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        Ok((terminal, Box::new(CrosstermInput)))
+    }
+
+    /// Give the terminal back as it was before `setup`.
+    pub fn restore(mut terminal: Terminal<GameBackend>) -> io::Result<()> {
+        disable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+        terminal.show_cursor()
+    }
+}
+
+#[cfg(feature = "termion")]
+mod with_termion {
+    use std::{
+        io::{self, Stdout},
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        thread,
+        time::Duration,
+    };
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use termion::{
+        event::Key,
+        input::TermRead,
+        raw::{IntoRawMode, RawTerminal},
+        screen::AlternateScreen,
+    };
+    use tui::{backend::TermionBackend, Terminal};
+
+    use super::{Input, Session};
+
+    pub type GameBackend = TermionBackend<AlternateScreen<RawTerminal<Stdout>>>;
+
+    /// Keys read with termion.
+    ///
+    /// termion can only wait for a key without a timeout, so a thread waits for
+    /// them and `poll` waits on the thread.
+    ///
+    /// termion doesn't tell when the terminal is resized either: `poll` compares its
+    /// size each time, and a change is read as an event that isn't a key, like crossterm
+    /// does, so the game draws again.
+    struct TermionInput {
+        keys: Receiver<io::Result<Key>>,
+        /// a key received by `poll`, not read yet
+        pending: Option<io::Result<Key>>,
+        /// the terminal size at the last `poll`
+        size: Option<(u16, u16)>,
+        /// a resize noticed by `poll`, not read yet
+        resized: bool,
+    }
+
+    impl TermionInput {
+        fn spawn() -> TermionInput {
+            let (sender, keys) = mpsc::channel();
+            thread::spawn(move || {
+                for key in io::stdin().keys() {
+                    // le jeu est fini, plus personne n'écoute
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            });
+            TermionInput {
+                keys,
+                pending: None,
+                size: termion::terminal_size().ok(),
+                resized: false,
+            }
+        }
+    }
+
+    impl Input for TermionInput {
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            let size = termion::terminal_size().ok();
+            if size != self.size {
+                self.size = size;
+                self.resized = true;
+            }
+            if self.pending.is_none() && !self.resized {
+                match self.keys.recv_timeout(timeout) {
+                    Ok(key) => self.pending = Some(key),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(io::ErrorKind::UnexpectedEof.into())
+                    }
+                }
+            }
+            Ok(self.pending.is_some() || self.resized)
+        }
+
+        fn read(&mut self) -> io::Result<Option<KeyEvent>> {
+            if std::mem::take(&mut self.resized) {
+                return Ok(None);
+            }
+            let key = match self.pending.take() {
+                Some(key) => key,
+                None => self
+                    .keys
+                    .recv()
+                    .map_err(|_| io::Error::from(io::ErrorKind::UnexpectedEof))?,
+            };
+            Ok(key_event(key?))
+        }
+    }
+
+    /// The crossterm key press matching a termion key, none for keys the game never uses.
+    fn key_event(key: Key) -> Option<KeyEvent> {
+        let (code, modifiers) = match key {
+            Key::Char('\n') => (KeyCode::Enter, KeyModifiers::NONE),
+            Key::Char('\t') => (KeyCode::Tab, KeyModifiers::NONE),
+            Key::Char(c) => (KeyCode::Char(c), KeyModifiers::NONE),
+            Key::Ctrl(c) => (KeyCode::Char(c), KeyModifiers::CONTROL),
+            Key::Alt(c) => (KeyCode::Char(c), KeyModifiers::ALT),
+            Key::Backspace => (KeyCode::Backspace, KeyModifiers::NONE),
+            Key::Esc => (KeyCode::Esc, KeyModifiers::NONE),
+            Key::Up => (KeyCode::Up, KeyModifiers::NONE),
+            Key::Down => (KeyCode::Down, KeyModifiers::NONE),
+            Key::Left => (KeyCode::Left, KeyModifiers::NONE),
+            Key::Right => (KeyCode::Right, KeyModifiers::NONE),
+            Key::PageUp => (KeyCode::PageUp, KeyModifiers::NONE),
+            Key::PageDown => (KeyCode::PageDown, KeyModifiers::NONE),
+            Key::Home => (KeyCode::Home, KeyModifiers::NONE),
+            Key::End => (KeyCode::End, KeyModifiers::NONE),
+            Key::F(n) => (KeyCode::F(n), KeyModifiers::NONE),
+            _ => return None,
+        };
+        Some(KeyEvent::new(code, modifiers))
+    }
+
+    /// Take over the terminal: raw mode and alternate screen.
+    pub fn setup() -> io::Result<Session> {
+        let stdout = AlternateScreen::from(io::stdout().into_raw_mode()?);
+        let terminal = Terminal::new(TermionBackend::new(stdout))?;
+        Ok((terminal, Box::new(TermionInput::spawn())))
+    }
+
+    /// Give the terminal back as it was before `setup`.
+    ///
+    /// termion leaves the alternate screen and raw mode when the terminal is dropped.
+    pub fn restore(mut terminal: Terminal<GameBackend>) -> io::Result<()> {
+        terminal.show_cursor()
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use tui::style::Color;
+
+/// Colors of the ui, configurable in the `theme` section of `config.json`.
+///
+/// Colors are written either as a name (`"green"`, `"darkgray"`, ...)
+/// or as an hexadecimal code like `"#ff8800"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// items you have enough code lines to buy
+    #[serde(with = "color")]
+    pub affordable: Color,
+    /// items that are too expensive for now
+    #[serde(with = "color")]
+    pub unaffordable: Color,
+    /// items you own as many as allowed
+    #[serde(with = "color")]
+    pub maxed: Color,
+    /// owned items producing a big share of the production
+    #[serde(with = "color")]
+    pub major: Color,
+    /// owned items producing a fair share of the production
+    #[serde(with = "color")]
+    pub minor: Color,
+    /// owned items producing next to nothing
+    #[serde(with = "color")]
+    pub negligible: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            affordable: Color::Green,
+            unaffordable: Color::DarkGray,
+            maxed: Color::Yellow,
+            major: Color::Green,
+            minor: Color::Yellow,
+            negligible: Color::DarkGray,
+        }
+    }
+}
+
+const COLOR_NAMES: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// Read a color name or `#rrggbb` code, ignoring case.
+pub fn parse_color(s: &str) -> Option<Color> {
+    let s = s.to_lowercase();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    COLOR_NAMES
+        .iter()
+        .find(|(name, _)| *name == s)
+        .map(|(_, color)| *color)
+}
+
+/// The text `parse_color` would read back as `color`.
+pub fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        color => COLOR_NAMES
+            .iter()
+            .find(|(_, c)| *c == color)
+            .map_or_else(|| "white".to_string(), |(name, _)| name.to_string()),
+    }
+}
+
+/// (De)serialize a `Color` as the text used in the config file.
+mod color {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use tui::style::Color;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::color_name(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_color(&s).ok_or_else(|| de::Error::custom(format!("unknown color {s:?}")))
+    }
+}